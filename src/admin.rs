@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use reqwest::Client;
+use tracing::{info, warn};
+
+use crate::telegram::{SendOptions, send_telegram_message};
+
+/// Default for `FAILURE_ALERT_AFTER`.
+pub const DEFAULT_FAILURE_ALERT_AFTER: u32 = 3;
+
+#[derive(Default)]
+struct FailureState {
+    consecutive: u32,
+    reported: bool,
+}
+
+/// Per-source failure streaks, so the admin hears once when a source breaks
+/// and once when it recovers instead of every cycle.
+pub struct FailureTracker {
+    alert_after: u32,
+    states: HashMap<String, FailureState>,
+}
+
+impl FailureTracker {
+    pub fn new(alert_after: u32) -> FailureTracker {
+        FailureTracker {
+            alert_after,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Records one failure. Returns a notice the first time the streak
+    /// reaches `alert_after` failures, or right away when `urgent`.
+    pub fn failure(&mut self, key: &str, error: &str, urgent: bool) -> Option<String> {
+        let state = self.states.entry(key.to_string()).or_default();
+        state.consecutive += 1;
+        if state.reported || (!urgent && state.consecutive < self.alert_after) {
+            return None;
+        }
+        state.reported = true;
+        Some(format!(
+            "🚨 {}: {} ({} بار پشت سر هم)",
+            key, error, state.consecutive
+        ))
+    }
+
+    /// Records a success. Returns a recovery notice if a failure was reported.
+    pub fn success(&mut self, key: &str) -> Option<String> {
+        let state = self.states.remove(key)?;
+        state
+            .reported
+            .then(|| format!("✅ {} دوباره کار می‌کنه", key))
+    }
+}
+
+/// Sends the notices gathered during a cycle as one message. Without an
+/// admin chat they are only logged.
+pub async fn send_admin_notices(
+    client: &Client,
+    bot_token: &str,
+    admin_chat_id: Option<&str>,
+    notices: &[String],
+) {
+    for notice in notices {
+        info!(notice = %notice, "📣 admin notice");
+    }
+    let Some(admin) = admin_chat_id.filter(|_| !notices.is_empty()) else {
+        return;
+    };
+    let text = notices.join("\n\n");
+    if let Err(e) =
+        send_telegram_message(client, bot_token, admin, &text, SendOptions::default()).await
+    {
+        warn!(chat_id = %admin, error = %e, "⚠️ ارسال گزارش به ادمین ناموفق");
+    }
+}
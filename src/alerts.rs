@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::currency::Currency;
+use crate::message::Unit;
+
+/// Default for `ALERT_COOLDOWN_SECS`.
+pub const DEFAULT_ALERT_COOLDOWN: Duration = Duration::from_secs(30 * 60);
+
+/// Values of one cycle keyed by currency code, in each line's own unit.
+pub type RateMap = HashMap<Currency, i64>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Above,
+    Below,
+}
+
+/// `ALERT_USD_ABOVE=1000000` → USD, Above, 1000000.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threshold {
+    pub currency: Currency,
+    pub direction: Direction,
+    pub value: i64,
+}
+
+/// `{ currency = "USD", above = 100000 }` in the bot config's `alerts` list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub currency: String,
+    pub above: Option<i64>,
+    pub below: Option<i64>,
+}
+
+impl AlertRule {
+    /// The rule as `ALERT_<CODE>_<ABOVE|BELOW>` names and levels.
+    pub fn env_entries(&self) -> Vec<(String, i64)> {
+        let code = self.currency.to_uppercase();
+        let mut entries = Vec::new();
+        if let Some(v) = self.above {
+            entries.push((format!("ALERT_{}_ABOVE", code), v));
+        }
+        if let Some(v) = self.below {
+            entries.push((format!("ALERT_{}_BELOW", code), v));
+        }
+        entries
+    }
+}
+
+/// A threshold that was crossed between two cycles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub currency: Currency,
+    pub direction: Direction,
+    pub level: i64,
+    pub current: i64,
+}
+
+/// Thresholds crossed going from `prev` to `rates`. Touching the level counts
+/// as crossing it; a currency missing from either map is skipped, so nothing
+/// fires on the first cycle.
+pub fn check_thresholds(
+    rates: &RateMap,
+    prev: &RateMap,
+    thresholds: &[Threshold],
+) -> Vec<AlertEvent> {
+    thresholds
+        .iter()
+        .filter_map(|t| {
+            let current = *rates.get(&t.currency)?;
+            let previous = *prev.get(&t.currency)?;
+            let crossed = match t.direction {
+                Direction::Above => previous < t.value && current >= t.value,
+                Direction::Below => previous > t.value && current <= t.value,
+            };
+            crossed.then(|| AlertEvent {
+                currency: t.currency.clone(),
+                direction: t.direction,
+                level: t.value,
+                current,
+            })
+        })
+        .collect()
+}
+
+/// Holds back a threshold that fired less than `cooldown` ago, so a rate
+/// hovering around the level doesn't alert on every crossing.
+pub struct AlertCooldown {
+    cooldown: Duration,
+    fired: HashMap<(Currency, i64), Instant>,
+}
+
+impl AlertCooldown {
+    pub fn new(cooldown: Duration) -> AlertCooldown {
+        AlertCooldown {
+            cooldown,
+            fired: HashMap::new(),
+        }
+    }
+
+    /// True if `event` may be sent now; records it as sent.
+    pub fn allow(&mut self, event: &AlertEvent) -> bool {
+        let now = Instant::now();
+        let key = (event.currency.clone(), event.level);
+        match self.fired.get(&key) {
+            Some(at) if now.duration_since(*at) < self.cooldown => false,
+            _ => {
+                self.fired.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+/// The alert text, with values rendered in the line's `unit`.
+pub fn format_alert(event: &AlertEvent, unit: Unit) -> String {
+    let (emoji, verb) = match event.direction {
+        Direction::Above => ("📈", "بالاتر از"),
+        Direction::Below => ("📉", "پایین‌تر از"),
+    };
+    format!(
+        "🚨 هشدار\n{} {} {} {} رفت — الان {} {}",
+        emoji,
+        event.currency,
+        verb,
+        unit.amount(event.level),
+        unit.amount(event.current),
+        unit.label()
+    )
+}
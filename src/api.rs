@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::routing::get;
+use chrono::SecondsFormat;
+use serde::Serialize;
+use tokio::sync::{broadcast, watch};
+use tracing::{info, warn};
+
+use crate::health::LastSuccess;
+use crate::message::{RateLine, Unit};
+use crate::metrics::{SharedMetrics, metrics_handler};
+use crate::storage::unix_now;
+use crate::telegram_poller::{RateSnapshot, SharedSnapshot};
+use crate::ws;
+
+#[derive(Clone)]
+struct ApiState {
+    snapshot: SharedSnapshot,
+    last_success: LastSuccess,
+    max_age: Duration,
+    updates: broadcast::Sender<RateSnapshot>,
+    shutdown: watch::Receiver<bool>,
+}
+
+#[derive(Serialize)]
+struct RateEntry {
+    name: String,
+    value: i64,
+    // "toman" یا "usd_cents"
+    unit: &'static str,
+    stale: bool,
+}
+
+#[derive(Serialize)]
+struct RatesBody {
+    last_success_at: u64,
+    rates: BTreeMap<String, RateEntry>,
+}
+
+async fn rates_handler(State(state): State<ApiState>) -> Json<RatesBody> {
+    let snapshot = state.snapshot.read().await;
+    let rates = snapshot
+        .lines
+        .iter()
+        .map(|l| {
+            let entry = RateEntry {
+                name: l.name.clone(),
+                value: l.unit.whole(l.value),
+                unit: match l.unit.base() {
+                    Unit::UsdCents => "usd_cents",
+                    _ => "toman",
+                },
+                stale: !l.is_fresh(),
+            };
+            (l.currency.to_string(), entry)
+        })
+        .collect();
+    Json(RatesBody {
+        last_success_at: state.last_success.load(Ordering::Relaxed),
+        rates,
+    })
+}
+
+#[derive(Serialize)]
+struct RateV1 {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    toman: Option<i64>,
+    // انس و بقیه‌ی نرخ‌های دلاری
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usd: Option<f64>,
+    // نسبت به آخرین پست؛ بار اول null
+    changed_pct: Option<f64>,
+    stale: bool,
+}
+
+#[derive(Serialize)]
+struct RatesV1Body {
+    timestamp: Option<String>,
+    rates: BTreeMap<String, RateV1>,
+}
+
+impl RateV1 {
+    fn from_line(line: &RateLine) -> RateV1 {
+        let value = line.unit.whole(line.value);
+        let previous = line.previous_value.map(|p| line.unit.whole(p));
+        let (toman, usd) = match line.unit.base() {
+            Unit::UsdCents => (None, Some(value as f64 / 100.0)),
+            _ => (Some(value), None),
+        };
+        RateV1 {
+            toman,
+            usd,
+            changed_pct: previous
+                .filter(|&p| p != 0)
+                .map(|p| ((value - p) as f64 / p as f64 * 10_000.0).round() / 100.0),
+            stale: !line.is_fresh(),
+        }
+    }
+}
+
+impl RatesV1Body {
+    fn from_snapshot(snapshot: &RateSnapshot) -> RatesV1Body {
+        RatesV1Body {
+            timestamp: snapshot
+                .taken_at
+                .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            rates: snapshot
+                .lines
+                .iter()
+                .map(|l| (l.currency.to_string(), RateV1::from_line(l)))
+                .collect(),
+        }
+    }
+}
+
+/// `snapshot` in the `GET /api/v1/rates` JSON shape.
+pub fn rates_json(snapshot: &RateSnapshot) -> String {
+    serde_json::to_string(&RatesV1Body::from_snapshot(snapshot)).unwrap_or_default()
+}
+
+async fn rates_v1_handler(State(state): State<ApiState>) -> Json<RatesV1Body> {
+    Json(RatesV1Body::from_snapshot(&*state.snapshot.read().await))
+}
+
+async fn ws_rates_handler(State(state): State<ApiState>, req: Request) -> Response {
+    ws::upgrade(
+        req,
+        state.snapshot,
+        state.updates.subscribe(),
+        state.shutdown,
+    )
+}
+
+async fn rate_v1_handler(
+    State(state): State<ApiState>,
+    Path(currency): Path<String>,
+) -> Result<Json<RateV1>, StatusCode> {
+    let snapshot = state.snapshot.read().await;
+    snapshot
+        .lines
+        .iter()
+        .find(|l| l.currency.code().eq_ignore_ascii_case(&currency))
+        .map(|l| Json(RateV1::from_line(l)))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn healthz_handler(State(state): State<ApiState>) -> StatusCode {
+    let last = state.last_success.load(Ordering::Relaxed);
+    let ago = (unix_now() as u64).saturating_sub(last);
+    if ago > state.max_age.as_secs() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    }
+}
+
+/// Serves `GET /rates` (the values of the last cycle as JSON),
+/// `GET /api/v1/rates` and `GET /api/v1/rates/{currency}` (the same, with the
+/// change since the last post; 404 for a currency not in the last cycle),
+/// `GET /ws/rates` (a WebSocket that gets every cycle from `updates`, see
+/// [`ws::upgrade`]), `GET /healthz` (503 once the last success is older than
+/// `max_age`) and `GET /metrics` on `addr`, until `shutdown` flips to true.
+pub async fn run_http_server(
+    addr: SocketAddr,
+    snapshot: SharedSnapshot,
+    updates: broadcast::Sender<RateSnapshot>,
+    last_success: LastSuccess,
+    metrics: SharedMetrics,
+    max_age: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(%addr, error = %e, "⚠️ سرور HTTP بالا نیومد");
+            return;
+        }
+    };
+    info!(%addr, "🌐 HTTP API listening on /rates, /api/v1/rates, /ws/rates, /healthz and /metrics");
+
+    let app = Router::new()
+        .route("/rates", get(rates_handler))
+        .route("/api/v1/rates", get(rates_v1_handler))
+        .route("/api/v1/rates/{currency}", get(rate_v1_handler))
+        .route("/ws/rates", get(ws_rates_handler))
+        .route("/healthz", get(healthz_handler))
+        .with_state(ApiState {
+            snapshot,
+            last_success,
+            max_age,
+            updates,
+            shutdown: shutdown.clone(),
+        })
+        .merge(
+            Router::new()
+                .route("/metrics", get(metrics_handler))
+                .with_state(metrics),
+        );
+    let stop = async move {
+        let _ = shutdown.wait_for(|stop| *stop).await;
+    };
+    if let Err(e) = axum::serve(listener, app)
+        .with_graceful_shutdown(stop)
+        .await
+    {
+        warn!(error = %e, "⚠️ سرور HTTP متوقف شد");
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::currency::Currency;
+use crate::message::{RateLine, Section};
+
+/// Default for `CACHE_EXPIRY_SECS`.
+pub const DEFAULT_CACHE_EXPIRY_SECS: u64 = 300;
+
+/// The last fresh value of one currency.
+#[derive(Clone, Copy)]
+pub struct RateCache {
+    pub value: i64,
+    pub fetched_at: Instant,
+    pub is_stale: bool,
+}
+
+struct Entry {
+    line: RateLine,
+    cache: RateCache,
+    // جای خط در پیام، تا مقدار کش‌شده همون‌جای قبلی بیاد
+    section: usize,
+    position: usize,
+}
+
+/// Fills in lines whose fetch failed with their last fresh value, until
+/// that value is older than `expiry`.
+pub struct LineCache {
+    entries: HashMap<Currency, Entry>,
+    expiry: Duration,
+}
+
+impl LineCache {
+    pub fn new(expiry: Duration) -> LineCache {
+        LineCache {
+            entries: HashMap::new(),
+            expiry,
+        }
+    }
+
+    /// Remembers this cycle's fresh lines and puts cached ones back where
+    /// a currency is missing. Expired entries are dropped.
+    pub fn apply(&mut self, sections: &mut [Section]) {
+        let now = Instant::now();
+        for (section, s) in sections.iter().enumerate() {
+            for (position, line) in s.lines.iter().enumerate() {
+                if !line.is_fresh() {
+                    continue;
+                }
+                self.entries.insert(
+                    line.currency.clone(),
+                    Entry {
+                        line: line.clone(),
+                        cache: RateCache {
+                            value: line.value,
+                            fetched_at: now,
+                            is_stale: false,
+                        },
+                        section,
+                        position,
+                    },
+                );
+            }
+        }
+
+        self.entries.retain(|code, e| {
+            let expired = now.duration_since(e.cache.fetched_at) > self.expiry;
+            if expired {
+                info!(currency = %code, "🗑 مقدار کش‌شده منقضی شد");
+            }
+            !expired
+        });
+
+        let mut missing: Vec<&mut Entry> = self
+            .entries
+            .values_mut()
+            .filter(|e| {
+                sections
+                    .get(e.section)
+                    .is_some_and(|s| s.lines.iter().all(|l| l.currency != e.line.currency))
+            })
+            .collect();
+        missing.sort_by_key(|e| (e.section, e.position));
+        for e in missing {
+            e.cache.is_stale = true;
+            let mut line = e.line.clone();
+            line.cached_secs = Some(now.duration_since(e.cache.fetched_at).as_secs());
+            line.previous_value = None;
+            let lines = &mut sections[e.section].lines;
+            lines.insert(e.position.min(lines.len()), line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(currency: Currency, toman: i64) -> RateLine {
+        RateLine::new(currency, "", "", toman, Some(toman - 100))
+    }
+
+    fn full_cycle() -> Vec<Section> {
+        vec![
+            Section::new(vec![
+                line(Currency::Usd, 102_540),
+                line(Currency::Eur, 113_200),
+                line(Currency::Aed, 27_900),
+            ]),
+            Section::new(vec![line(Currency::Try, 2_999)]),
+        ]
+    }
+
+    fn codes(section: &Section) -> Vec<&str> {
+        section.lines.iter().map(|l| l.currency.code()).collect()
+    }
+
+    #[test]
+    fn missing_lines_come_back_cached_in_place() {
+        let mut cache = LineCache::new(Duration::from_secs(300));
+        cache.apply(&mut full_cycle());
+
+        // دلار و لیر این چرخه نیومدن
+        let mut sections = vec![
+            Section::new(vec![
+                line(Currency::Eur, 113_300),
+                line(Currency::Aed, 27_950),
+            ]),
+            Section::new(vec![]),
+        ];
+        cache.apply(&mut sections);
+
+        assert_eq!(codes(&sections[0]), ["USD", "EUR", "AED"]);
+        assert_eq!(codes(&sections[1]), ["TRY"]);
+        let usd = &sections[0].lines[0];
+        assert_eq!(usd.value, 102_540);
+        assert_eq!(usd.cached_secs, Some(0));
+        // مقدار کش‌شده فلش تغییر نداره
+        assert_eq!(usd.previous_value, None);
+        assert!(sections[0].lines[1].is_fresh());
+    }
+
+    #[test]
+    fn cached_lines_are_not_cached_again() {
+        let mut cache = LineCache::new(Duration::from_secs(300));
+        cache.apply(&mut full_cycle());
+        let mut partial = vec![Section::new(vec![]), Section::new(vec![])];
+        cache.apply(&mut partial);
+        let mut partial = vec![Section::new(vec![]), Section::new(vec![])];
+        cache.apply(&mut partial);
+        assert_eq!(codes(&partial[0]), ["USD", "EUR", "AED"]);
+        assert!(partial[0].lines.iter().all(|l| !l.is_fresh()));
+    }
+
+    #[test]
+    fn expired_lines_are_omitted() {
+        let mut cache = LineCache::new(Duration::ZERO);
+        cache.apply(&mut full_cycle());
+        std::thread::sleep(Duration::from_millis(5));
+        let mut sections = vec![
+            Section::new(vec![line(Currency::Eur, 113_300)]),
+            Section::new(vec![]),
+        ];
+        cache.apply(&mut sections);
+        assert_eq!(codes(&sections[0]), ["EUR"]);
+        assert!(sections[1].lines.is_empty());
+    }
+}
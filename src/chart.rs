@@ -0,0 +1,185 @@
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+/// How far back a `/chart` request looks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChartWindow {
+    Day,
+    Week,
+    Month,
+}
+
+impl ChartWindow {
+    pub fn parse(raw: &str) -> Option<ChartWindow> {
+        match raw.to_ascii_lowercase().as_str() {
+            "1d" => Some(ChartWindow::Day),
+            "7d" => Some(ChartWindow::Week),
+            "30d" => Some(ChartWindow::Month),
+            _ => None,
+        }
+    }
+
+    pub fn secs(self) -> i64 {
+        match self {
+            ChartWindow::Day => 86_400,
+            ChartWindow::Week => 7 * 86_400,
+            ChartWindow::Month => 30 * 86_400,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChartWindow::Day => "۲۴ ساعت",
+            ChartWindow::Week => "۷ روز",
+            ChartWindow::Month => "۳۰ روز",
+        }
+    }
+}
+
+const WIDTH: usize = 800;
+const HEIGHT: usize = 400;
+const MARGIN: i64 = 24;
+const GRID_LINES: i64 = 4;
+
+type Rgb = [u8; 3];
+const BACKGROUND: Rgb = [255, 255, 255];
+const GRID: Rgb = [232, 232, 232];
+const AXIS: Rgb = [120, 120, 120];
+const LINE: Rgb = [33, 113, 181];
+
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Canvas {
+        Canvas {
+            pixels: BACKGROUND.repeat(WIDTH * HEIGHT),
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64, color: Rgb) {
+        if x < 0 || y < 0 || x >= WIDTH as i64 || y >= HEIGHT as i64 {
+            return;
+        }
+        let i = (y as usize * WIDTH + x as usize) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&color);
+    }
+
+    fn hline(&mut self, y: i64, color: Rgb) {
+        for x in MARGIN..=WIDTH as i64 - MARGIN {
+            self.set(x, y, color);
+        }
+    }
+
+    fn vline(&mut self, x: i64, color: Rgb) {
+        for y in MARGIN..=HEIGHT as i64 - MARGIN {
+            self.set(x, y, color);
+        }
+    }
+
+    // Bresenham، دو پیکسل ضخامت تا روی موبایل دیده بشه
+    fn line(&mut self, (mut x0, mut y0): (i64, i64), (x1, y1): (i64, i64), color: Rgb) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.set(x0, y0, color);
+            self.set(x0, y0 + 1, color);
+            self.set(x0 + 1, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    fn into_png(self) -> Vec<u8> {
+        // هر سطر با بایت فیلتر صفر (None) شروع می‌شه
+        let mut raw = Vec::with_capacity((WIDTH * 3 + 1) * HEIGHT);
+        for row in self.pixels.chunks(WIDTH * 3) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(WIDTH as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(HEIGHT as u32).to_be_bytes());
+        // عمق ۸ بیت، RGB، بدون interlace
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut png, b"IHDR", &ihdr);
+        png_chunk(&mut png, b"IDAT", &compress_to_vec_zlib(&raw, 6));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Renders `(unix time, value)` points, oldest first, as a PNG line chart
+/// with light horizontal gridlines. Expects at least two points.
+pub fn render_line_chart(points: &[(i64, i64)]) -> Vec<u8> {
+    let mut canvas = Canvas::new();
+    let plot_w = WIDTH as i64 - 2 * MARGIN;
+    let plot_h = HEIGHT as i64 - 2 * MARGIN;
+
+    for i in 0..=GRID_LINES {
+        canvas.hline(MARGIN + plot_h * i / GRID_LINES, GRID);
+    }
+    canvas.vline(MARGIN, AXIS);
+    canvas.hline(HEIGHT as i64 - MARGIN, AXIS);
+
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return canvas.into_png();
+    };
+    let t_span = (last.0 - first.0).max(1);
+    let low = points.iter().map(|p| p.1).min().unwrap_or(0);
+    let high = points.iter().map(|p| p.1).max().unwrap_or(0);
+    // کمی فاصله بالا و پایین تا خط به لبه‌ها نچسبه
+    let pad = ((high - low) / 20).max(1);
+    let (low, high) = (low - pad, high + pad);
+    let v_span = high - low;
+
+    let to_xy = |&(t, v): &(i64, i64)| {
+        let x = MARGIN + (t - first.0) * plot_w / t_span;
+        let y = HEIGHT as i64 - MARGIN - (v - low) * plot_h / v_span;
+        (x, y)
+    };
+    for pair in points.windows(2) {
+        canvas.line(to_xy(&pair[0]), to_xy(&pair[1]), LINE);
+    }
+    canvas.into_png()
+}
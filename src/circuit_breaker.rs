@@ -0,0 +1,205 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::error::BotError;
+
+/// Default for `CIRCUIT_FAILURES`.
+pub const DEFAULT_CIRCUIT_FAILURES: u32 = 5;
+/// Default for `CIRCUIT_OPEN_SECS`.
+pub const DEFAULT_CIRCUIT_OPEN_SECS: u64 = 300;
+
+/// Where a [`CircuitBreaker`] is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    /// Calls go through; failures are counted.
+    Closed,
+    /// Calls are refused until `open_for` has passed since this instant.
+    Open(Instant),
+    /// One trial call decides between closing and opening again.
+    HalfOpen,
+}
+
+struct Inner {
+    state: State,
+    // خطاهای پشت سر هم در حالت Closed
+    failures: u32,
+}
+
+/// Stops calling a source after `threshold` failures in a row, for
+/// `open_for`, then lets a single call through to see if it recovered.
+pub struct CircuitBreaker {
+    name: String,
+    threshold: u32,
+    open_for: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str, threshold: u32, open_for: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            name: name.to_string(),
+            threshold,
+            open_for,
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                failures: 0,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.lock().state
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // قفل فقط برای خوندن و نوشتن وضعیته؛ panic وسطش وضعیت رو خراب نمی‌کنه
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `f` unless the circuit is open, in which case it fails fast with
+    /// [`BotError::CircuitOpen`]. The outcome moves the state along.
+    pub async fn call<F, Fut, T>(&self, f: F) -> Result<T, BotError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, BotError>>,
+    {
+        self.before_call()?;
+        let result = f().await;
+        match &result {
+            Ok(_) => self.on_success(),
+            Err(_) => self.on_failure(),
+        }
+        result
+    }
+
+    fn before_call(&self) -> Result<(), BotError> {
+        let mut inner = self.lock();
+        if let State::Open(since) = inner.state {
+            let elapsed = since.elapsed();
+            if elapsed < self.open_for {
+                return Err(BotError::CircuitOpen {
+                    source_name: self.name.clone(),
+                    retry_in_secs: (self.open_for - elapsed).as_secs(),
+                });
+            }
+            info!(source = %self.name, "🔌 circuit half-open, trying one request");
+            inner.state = State::HalfOpen;
+        }
+        Ok(())
+    }
+
+    fn on_success(&self) {
+        let mut inner = self.lock();
+        if inner.state == State::HalfOpen {
+            info!(source = %self.name, "🔌 circuit closed again");
+        }
+        inner.state = State::Closed;
+        inner.failures = 0;
+    }
+
+    fn on_failure(&self) {
+        let mut inner = self.lock();
+        inner.failures += 1;
+        let trip = inner.state == State::HalfOpen || inner.failures >= self.threshold;
+        if trip {
+            warn!(
+                source = %self.name,
+                failures = inner.failures,
+                open_secs = self.open_for.as_secs(),
+                "🔌 circuit opened, skipping this source for a while"
+            );
+            inner.state = State::Open(Instant::now());
+            inner.failures = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::FetchError;
+
+    fn failing() -> Result<(), BotError> {
+        Err(BotError::Fetch(FetchError::UpstreamRejected {
+            source_name: "test".to_string(),
+            reason: "down".to_string(),
+        }))
+    }
+
+    async fn fail(breaker: &CircuitBreaker) {
+        assert!(breaker.call(|| async { failing() }).await.is_err());
+    }
+
+    // یک بار می‌افته و بعد از گذشتن open_for در حالت HalfOpen منتظر می‌مونه
+    async fn half_open(open_for: Duration) -> CircuitBreaker {
+        let breaker = CircuitBreaker::new("test", 1, open_for);
+        fail(&breaker).await;
+        std::thread::sleep(open_for);
+        breaker
+    }
+
+    #[tokio::test]
+    async fn opens_at_the_failure_threshold() {
+        let breaker = CircuitBreaker::new("test", 3, Duration::from_secs(60));
+        fail(&breaker).await;
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), State::Closed);
+        fail(&breaker).await;
+        assert!(matches!(breaker.state(), State::Open(_)));
+
+        let mut called = false;
+        let err = breaker
+            .call(|| async {
+                called = true;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert!(!called);
+        assert!(matches!(err, BotError::CircuitOpen { .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::from_secs(60));
+        fail(&breaker).await;
+        breaker.call(|| async { Ok(()) }).await.unwrap();
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), State::Closed);
+    }
+
+    #[tokio::test]
+    async fn goes_half_open_after_open_for() {
+        let breaker = half_open(Duration::from_millis(20)).await;
+        assert!(matches!(breaker.state(), State::Open(_)));
+        breaker
+            .call(|| async {
+                assert_eq!(breaker.state(), State::HalfOpen);
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn half_open_success_closes() {
+        let breaker = half_open(Duration::from_millis(20)).await;
+        breaker.call(|| async { Ok(()) }).await.unwrap();
+        assert_eq!(breaker.state(), State::Closed);
+    }
+
+    #[tokio::test]
+    async fn half_open_failure_reopens() {
+        let breaker = CircuitBreaker::new("test", 3, Duration::from_millis(20));
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        // در HalfOpen یک خطا کافیه، نه سه تا
+        fail(&breaker).await;
+        assert!(matches!(breaker.state(), State::Open(_)));
+    }
+}
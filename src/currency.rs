@@ -0,0 +1,204 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A currency or asset the bot shows. The ones the bot computes or treats
+/// specially have their own variant; everything else from the config (gold,
+/// commodities, extra forex) is `Custom` with its upper-case code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Currency {
+    Usd,
+    Eur,
+    Aed,
+    Cny,
+    Gbp,
+    Try,
+    Usdt,
+    Btc,
+    Eth,
+    Custom(String),
+}
+
+impl Currency {
+    pub fn code(&self) -> &str {
+        match self {
+            Currency::Usd => "USD",
+            Currency::Eur => "EUR",
+            Currency::Aed => "AED",
+            Currency::Cny => "CNY",
+            Currency::Gbp => "GBP",
+            Currency::Try => "TRY",
+            Currency::Usdt => "USDT",
+            Currency::Btc => "BTC",
+            Currency::Eth => "ETH",
+            Currency::Custom(code) => code,
+        }
+    }
+
+    /// Default Persian name; config entries may override it.
+    pub fn name(&self) -> &str {
+        match self {
+            Currency::Usd => "دلار",
+            Currency::Eur => "یورو",
+            Currency::Aed => "درهم",
+            Currency::Cny => "یوآن چین",
+            Currency::Gbp => "پوند انگلیس",
+            Currency::Try => "لیر ترکیه",
+            Currency::Usdt => "تتر",
+            Currency::Btc => "بیت‌کوین",
+            Currency::Eth => "اتریوم",
+            Currency::Custom(code) => code,
+        }
+    }
+
+    /// Default emoji; config entries may override it.
+    pub fn emoji(&self) -> &str {
+        match self {
+            Currency::Usd => "💵",
+            Currency::Eur => "💶",
+            Currency::Aed => "🇦🇪",
+            Currency::Cny => "🇨🇳",
+            Currency::Gbp => "💷",
+            Currency::Try => "🇹🇷",
+            Currency::Usdt => "₮",
+            Currency::Btc => "₿",
+            Currency::Eth => "Ξ",
+            Currency::Custom(_) => "💱",
+        }
+    }
+}
+
+impl From<&str> for Currency {
+    fn from(code: &str) -> Currency {
+        match code.trim().to_ascii_uppercase().as_str() {
+            "USD" => Currency::Usd,
+            "EUR" => Currency::Eur,
+            "AED" => Currency::Aed,
+            "CNY" => Currency::Cny,
+            "GBP" => Currency::Gbp,
+            "TRY" => Currency::Try,
+            "USDT" => Currency::Usdt,
+            "BTC" => Currency::Btc,
+            "ETH" => Currency::Eth,
+            other => Currency::Custom(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for Currency {
+    fn from(code: String) -> Currency {
+        Currency::from(code.as_str())
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> String {
+        currency.code().to_string()
+    }
+}
+
+impl PartialEq<str> for Currency {
+    fn eq(&self, code: &str) -> bool {
+        self.code().eq_ignore_ascii_case(code)
+    }
+}
+
+impl PartialEq<&str> for Currency {
+    fn eq(&self, code: &&str) -> bool {
+        self == *code
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// A whole number of rials, the unit tgju and Nobitex quote in. Every
+/// conversion to toman (rial / 10) goes through [`Rial::toman`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rial(pub i64);
+
+impl Rial {
+    /// Rounded to the nearest toman, halves away from zero. This is the only
+    /// rounding rule; direct and dollar-derived prices all go through it.
+    pub fn toman(self) -> i64 {
+        let (toman, rest) = (self.0 / 10, self.0 % 10);
+        match rest {
+            5.. => toman + 1,
+            ..=-5 => toman - 1,
+            _ => toman,
+        }
+    }
+
+    /// Toman × 10^`places` for [`Unit::TomanDecimals`]: 783 rial with one
+    /// place is `783`, shown as 78.3. Whole rials already are tenths of a
+    /// toman, so only `places = 0` rounds, through [`Rial::toman`].
+    ///
+    /// [`Unit::TomanDecimals`]: crate::message::Unit::TomanDecimals
+    pub fn toman_scaled(self, places: u32) -> i64 {
+        match places {
+            0 => self.toman(),
+            places => self.0 * 10_i64.pow(places - 1),
+        }
+    }
+
+    /// The price of `amount` units priced at this rate each, to the nearest
+    /// rial.
+    pub fn times(self, amount: f64) -> Rial {
+        Rial((self.0 as f64 * amount).round() as i64)
+    }
+
+    /// This amount split into `parts`, e.g. rial per lira from rial per
+    /// dollar and lira per dollar.
+    pub fn per(self, parts: f64) -> Rial {
+        Rial((self.0 as f64 / parts).round() as i64)
+    }
+}
+
+/// Toman price of something worth `foreign_usd` dollars, with the dollar at
+/// `usd_rate_rial`.
+pub fn compute_toman_price(usd_rate_rial: i64, foreign_usd: f64) -> i64 {
+    Rial(usd_rate_rial).times(foreign_usd).toman()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toman_price_from_dollar_rate() {
+        // دلار ۱٬۰۲۵٬۴۰۰ ریال، بیت‌کوین ۶۷٬۰۱۲.۵ دلار
+        assert_eq!(compute_toman_price(1_025_400, 67_012.5), 6_871_461_750);
+        assert_eq!(compute_toman_price(1_025_400, 1.0), 102_540);
+        assert_eq!(compute_toman_price(1_025_400, 0.0), 0);
+    }
+
+    #[test]
+    fn toman_price_rounds_like_direct_rates() {
+        // ۰.۱ دلار = ۱۰٬۲۵۴ ریال = ۱٬۰۲۵.۴ تومان
+        assert_eq!(compute_toman_price(102_540, 0.1), 1_025);
+        assert_eq!(compute_toman_price(1_025_405, 1.0), Rial(1_025_405).toman());
+    }
+
+    #[test]
+    fn toman_scaled_keeps_decimal_places() {
+        assert_eq!(Rial(783).toman_scaled(0), 78);
+        assert_eq!(Rial(783).toman_scaled(1), 783);
+        assert_eq!(Rial(783).toman_scaled(2), 7_830);
+        assert_eq!(Rial(785).toman_scaled(0), 79);
+    }
+
+    #[test]
+    fn rial_to_toman_rounding() {
+        assert_eq!(Rial(1_025_405).toman(), 102_541);
+        assert_eq!(Rial(1_025_404).toman(), 102_540);
+        assert_eq!(Rial(1_025_401).toman(), 102_540);
+        assert_eq!(Rial(-15).toman(), -2);
+        // ۱٬۰۲۵٬۴۰۰ / ۳۴.۲ = ۲۹٬۹۸۲.۴۶ ریال
+        assert_eq!(Rial(1_025_400).per(34.2), Rial(29_982));
+        assert_eq!(Rial(1_025_400).per(34.2).toman(), 2_998);
+    }
+}
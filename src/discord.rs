@@ -0,0 +1,68 @@
+use reqwest::Client;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::error::BotError;
+
+/// Name the webhook posts under.
+pub const DISCORD_USERNAME: &str = "نرخ ارز";
+// بیشتر از این رو Discord با 400 رد می‌کنه
+const DISCORD_MAX_CHARS: usize = 2000;
+
+#[derive(Serialize)]
+struct WebhookBody<'a> {
+    content: &'a str,
+    username: &'a str,
+}
+
+/// Removes the bidi control characters (LRM, RLM, embeddings, isolates and
+/// the Arabic letter mark) that Telegram handles but Discord shows as boxes
+/// or uses to scramble the line order.
+pub fn strip_bidi_marks(text: &str) -> String {
+    text.chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                '\u{200e}' | '\u{200f}' | '\u{061c}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+            )
+        })
+        .collect()
+}
+
+/// Posts `text` as plain content to a Discord webhook. Text past Discord's
+/// 2000-character limit is cut off.
+pub async fn send_discord_message(
+    client: &Client,
+    webhook_url: &str,
+    text: &str,
+) -> Result<(), BotError> {
+    let mut content = strip_bidi_marks(text);
+    if let Some((cut, _)) = content.char_indices().nth(DISCORD_MAX_CHARS - 1) {
+        content.truncate(cut);
+        content.push('…');
+    }
+    let resp = client
+        .post(webhook_url)
+        .json(&WebhookBody {
+            content: &content,
+            username: DISCORD_USERNAME,
+        })
+        .send()
+        .await
+        .map_err(|source| BotError::WebhookSend {
+            service: "Discord",
+            source,
+        })?;
+
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    if status.is_success() {
+        debug!(status = status.as_u16(), "📨 discord response");
+        return Ok(());
+    }
+    Err(BotError::WebhookApi {
+        service: "Discord",
+        status,
+        description: body,
+    })
+}
@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Client;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::telegram::{SendOptions, log_send_failure, send_telegram_message};
+
+/// Messages kept at most; the oldest is dropped past this.
+pub const MAX_DLQ_SIZE: usize = 20;
+/// How often the queue is retried.
+pub const DLQ_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A message that couldn't be sent, waiting for another try.
+pub struct PendingMessage {
+    pub chat_id: String,
+    pub text: String,
+    pub options: SendOptions,
+    pub failed_at: Instant,
+}
+
+impl PendingMessage {
+    pub fn new(chat_id: &str, text: &str, options: SendOptions) -> PendingMessage {
+        PendingMessage {
+            chat_id: chat_id.to_string(),
+            text: text.to_string(),
+            options,
+            failed_at: Instant::now(),
+        }
+    }
+}
+
+/// Messages whose send failed with a transient error (network, 5xx, 429),
+/// shared between the main loop and [`run_dlq_drainer`].
+#[derive(Clone, Default)]
+pub struct DeadLetterQueue(Arc<Mutex<VecDeque<PendingMessage>>>);
+
+impl DeadLetterQueue {
+    /// Queues `message`, dropping the oldest one if the queue is full.
+    pub fn push(&self, message: PendingMessage) {
+        let Ok(mut queue) = self.0.lock() else {
+            return;
+        };
+        if queue.len() >= MAX_DLQ_SIZE
+            && let Some(dropped) = queue.pop_front()
+        {
+            warn!(
+                chat_id = %dropped.chat_id,
+                age_secs = dropped.failed_at.elapsed().as_secs(),
+                "🗑 صف پیام‌های ناموفق پر شد، قدیمی‌ترین پیام دور ریخته شد"
+            );
+        }
+        queue.push_back(message);
+        info!(
+            queued = queue.len(),
+            "📥 پیام ناموفق برای تلاش دوباره در صف رفت"
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().map_or(0, |q| q.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn pop_front(&self) -> Option<PendingMessage> {
+        self.0.lock().ok()?.pop_front()
+    }
+
+    fn push_front(&self, message: PendingMessage) {
+        if let Ok(mut queue) = self.0.lock() {
+            queue.push_front(message);
+        }
+    }
+}
+
+/// Every [`DLQ_RETRY_INTERVAL`], sends queued messages in order. A transient
+/// failure puts the message back and waits for the next round; any other
+/// error drops it. Stops when `shutdown` flips to true.
+pub async fn run_dlq_drainer(
+    client: Client,
+    bot_token: String,
+    queue: DeadLetterQueue,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(DLQ_RETRY_INTERVAL) => {}
+            _ = shutdown.wait_for(|stop| *stop) => return,
+        }
+        while let Some(message) = queue.pop_front() {
+            let sent = send_telegram_message(
+                &client,
+                &bot_token,
+                &message.chat_id,
+                &message.text,
+                message.options,
+            )
+            .await;
+            match sent {
+                Ok(_) => info!(
+                    chat_id = %message.chat_id,
+                    delay_secs = message.failed_at.elapsed().as_secs(),
+                    "📤 پیام صف‌شده ارسال شد"
+                ),
+                Err(e) if e.is_transient() => {
+                    queue.push_front(message);
+                    break;
+                }
+                Err(e) => {
+                    log_send_failure(&message.chat_id, &e);
+                    warn!(chat_id = %message.chat_id, "🗑 پیام صف‌شده دور ریخته شد");
+                }
+            }
+        }
+    }
+}
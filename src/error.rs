@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// Failures while pulling a rate from an upstream source (tgju, BtcTurk).
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Request error for {url}: {source}")]
+    Http {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Read body error for {url}: {source}")]
+    Body {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Selector parse error for '{selector}': {message}")]
+    InvalidSelector { selector: String, message: String },
+
+    #[error("Selector '{selector}' not found on {url}")]
+    SelectorNotFound { url: String, selector: String },
+
+    #[error("Parse int error for '{raw}' : {source}")]
+    ParseInt {
+        raw: String,
+        #[source]
+        source: std::num::ParseIntError,
+    },
+
+    #[error("Parse float error for '{raw}' : {source}")]
+    ParseFloat {
+        raw: String,
+        #[source]
+        source: std::num::ParseFloatError,
+    },
+
+    #[error("Number '{raw}' does not fit in a price")]
+    OutOfRange { raw: String },
+
+    #[error("json parse error: {source} / body: {body}")]
+    JsonDecode {
+        body: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{source_name} responded with {reason}")]
+    UpstreamRejected { source_name: String, reason: String },
+
+    #[error("{source_name} has no {pair} ticker, returned: {returned}")]
+    PairNotFound {
+        source_name: String,
+        pair: String,
+        returned: String,
+    },
+}
+
+impl FetchError {
+    /// Network-level failures are worth retrying; a missing selector or an
+    /// unparsable number will look the same a second later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            FetchError::Http { .. } | FetchError::Body { .. } | FetchError::UpstreamRejected { .. }
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BotError {
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+
+    #[error("{source} (after {attempts} attempts)")]
+    RetriesExhausted {
+        attempts: u32,
+        #[source]
+        source: Box<BotError>,
+    },
+
+    #[error("Telegram request error: {0}")]
+    TelegramSend(#[source] reqwest::Error),
+
+    #[error("Telegram responded with {status}: {description}")]
+    TelegramApi {
+        status: reqwest::StatusCode,
+        description: String,
+    },
+
+    #[error("Database error: {0}")]
+    Storage(#[from] sqlx::Error),
+
+    #[error("Telegram rate limit hit, retry after {retry_after}s")]
+    TelegramRateLimited { retry_after: u64 },
+
+    #[error("Rate {value} for {currency} is outside [{min}, {max}]")]
+    RateOutOfBounds {
+        currency: String,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+
+    #[error("Telegram response decode error: {source} / body: {body}")]
+    TelegramDecode {
+        body: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("{service} webhook request error: {source}")]
+    WebhookSend {
+        service: &'static str,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("{service} webhook responded with {status}: {description}")]
+    WebhookApi {
+        service: &'static str,
+        status: reqwest::StatusCode,
+        description: String,
+    },
+
+    #[error("Circuit for {source_name} is open, next try in {retry_in_secs}s")]
+    CircuitOpen {
+        source_name: String,
+        retry_in_secs: u64,
+    },
+}
+
+impl BotError {
+    /// The underlying error, looking through retry wrappers.
+    pub fn root(&self) -> &BotError {
+        match self {
+            BotError::RetriesExhausted { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    pub fn fetch_error(&self) -> Option<&FetchError> {
+        match self.root() {
+            BotError::Fetch(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Telegram's 400 answers meaning the message we try to edit is gone
+    /// (deleted, or an id that no longer belongs to this chat).
+    pub fn is_message_not_found(&self) -> bool {
+        match self.root() {
+            BotError::TelegramApi {
+                status,
+                description,
+            } if *status == reqwest::StatusCode::BAD_REQUEST => {
+                description.contains("message to edit not found")
+                    || description.contains("MESSAGE_ID_INVALID")
+                    || description.contains("message can't be edited")
+            }
+            _ => false,
+        }
+    }
+
+    /// Telegram's 403 for a private chat that will never accept our
+    /// messages again: the user blocked the bot or deleted their account.
+    pub fn is_blocked_by_user(&self) -> bool {
+        match self.root() {
+            BotError::TelegramApi {
+                status,
+                description,
+            } if *status == reqwest::StatusCode::FORBIDDEN => {
+                description.contains("bot was blocked by the user")
+                    || description.contains("user is deactivated")
+            }
+            _ => false,
+        }
+    }
+
+    /// How long Telegram asked us to back off, if this was a 429.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.root() {
+            BotError::TelegramRateLimited { retry_after } => {
+                Some(Duration::from_secs(*retry_after))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        match self.root() {
+            BotError::Fetch(e) => e.is_transient(),
+            BotError::TelegramSend(_)
+            | BotError::TelegramRateLimited { .. }
+            | BotError::WebhookSend { .. } => true,
+            BotError::TelegramApi { status, .. } => status.is_server_error(),
+            BotError::WebhookApi { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            BotError::RetriesExhausted { .. }
+            | BotError::TelegramDecode { .. }
+            | BotError::RateOutOfBounds { .. }
+            | BotError::CircuitOpen { .. }
+            | BotError::Storage(_) => false,
+        }
+    }
+
+    /// The source was skipped because its circuit breaker is open.
+    pub fn is_circuit_open(&self) -> bool {
+        matches!(self.root(), BotError::CircuitOpen { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn telegram(status: u16, description: &str) -> BotError {
+        BotError::TelegramApi {
+            status: reqwest::StatusCode::from_u16(status).unwrap(),
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn blocked_by_user_is_a_403() {
+        assert!(telegram(403, "Forbidden: bot was blocked by the user").is_blocked_by_user());
+        assert!(telegram(403, "Forbidden: user is deactivated").is_blocked_by_user());
+        // ربات از کانال بیرون انداخته شده؛ مشترک نیست که حذف بشه
+        assert!(
+            !telegram(403, "Forbidden: bot was kicked from the channel chat").is_blocked_by_user()
+        );
+        assert!(!telegram(400, "Bad Request: chat not found").is_blocked_by_user());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::io;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::info;
+
+/// Appends one row per cycle to `EXPORT_CSV`: the UTC time followed by the
+/// toman value of each configured currency, in the order given to `new`. A
+/// currency missing from a cycle leaves its cell empty. A file left by a run
+/// with a different currency list is renamed aside and a fresh one started.
+pub struct CsvExport {
+    path: String,
+    columns: Vec<String>,
+    header_checked: bool,
+}
+
+impl CsvExport {
+    pub fn new(path: &str, columns: Vec<String>) -> CsvExport {
+        CsvExport {
+            path: path.to_string(),
+            columns,
+            header_checked: false,
+        }
+    }
+
+    fn header(&self) -> String {
+        format!("timestamp_utc,{}", self.columns.join(","))
+    }
+
+    // اگه هدر فایل قبلی با ستون‌های فعلی نخونه، فایل کنار گذاشته می‌شه تا
+    // مقدارها زیر ستون اشتباه نرن یا بی‌صدا حذف نشن
+    async fn rotate_if_changed(&self, at: DateTime<Utc>) -> io::Result<()> {
+        let file = match File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut header = String::new();
+        BufReader::new(file).read_line(&mut header).await?;
+        if header.is_empty() || header.trim_end() == self.header() {
+            return Ok(());
+        }
+        let rotated = format!("{}.{}", self.path, at.format("%Y%m%dT%H%M%SZ"));
+        fs::rename(&self.path, &rotated).await?;
+        info!(path = %self.path, rotated = %rotated, "📁 ستون‌های CSV عوض شد، فایل قبلی کنار گذاشته شد");
+        Ok(())
+    }
+
+    pub async fn append(&mut self, at: DateTime<Utc>, rows: &[(&str, i64)]) -> io::Result<()> {
+        if !self.header_checked {
+            self.rotate_if_changed(at).await?;
+            self.header_checked = true;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        if file.metadata().await?.len() == 0 {
+            file.write_all(format!("{}\n", self.header()).as_bytes())
+                .await?;
+        }
+
+        let values: HashMap<&str, i64> = rows.iter().copied().collect();
+        let mut line = at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        for code in &self.columns {
+            line.push(',');
+            if let Some(value) = values.get(code.as_str()) {
+                line.push_str(&value.to_string());
+            }
+        }
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_csv(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("peybot_{}_{}.csv", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn columns(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn header_is_written_once() {
+        let path = temp_csv("export_two_cycles");
+        let first = Utc.with_ymd_and_hms(2024, 9, 5, 10, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2024, 9, 5, 10, 1, 0).unwrap();
+
+        let mut export = CsvExport::new(&path, columns(&["USD", "EUR"]));
+        export
+            .append(first, &[("USD", 102_540), ("EUR", 113_200)])
+            .await
+            .unwrap();
+        // EUR این چرخه نیومده
+        export.append(second, &[("USD", 102_600)]).await.unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            written,
+            "timestamp_utc,USD,EUR\n\
+             2024-09-05T10:00:00Z,102540,113200\n\
+             2024-09-05T10:01:00Z,102600,\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn restart_reuses_the_existing_header() {
+        let path = temp_csv("export_restart");
+        let at = Utc.with_ymd_and_hms(2024, 9, 5, 10, 0, 0).unwrap();
+
+        CsvExport::new(&path, columns(&["USD", "EUR"]))
+            .append(at, &[("USD", 102_540), ("EUR", 113_200)])
+            .await
+            .unwrap();
+        // ترتیب ستون‌ها از تنظیمات میاد، نه از این چرخه
+        CsvExport::new(&path, columns(&["USD", "EUR"]))
+            .append(at, &[("EUR", 113_300), ("USD", 102_600)])
+            .await
+            .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written.matches("timestamp_utc").count(), 1);
+        assert!(written.ends_with("2024-09-05T10:00:00Z,102600,113300\n"));
+    }
+
+    #[tokio::test]
+    async fn changed_currencies_start_a_new_file() {
+        let path = temp_csv("export_rotate");
+        let at = Utc.with_ymd_and_hms(2024, 9, 5, 10, 0, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2024, 9, 6, 8, 30, 0).unwrap();
+
+        CsvExport::new(&path, columns(&["USD"]))
+            .append(at, &[("USD", 102_540)])
+            .await
+            .unwrap();
+        // GBP به تنظیمات اضافه شده
+        CsvExport::new(&path, columns(&["USD", "GBP"]))
+            .append(later, &[("USD", 102_600), ("GBP", 131_000)])
+            .await
+            .unwrap();
+
+        let rotated = format!("{}.20240906T083000Z", path);
+        let old = std::fs::read_to_string(&rotated).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(old, "timestamp_utc,USD\n2024-09-05T10:00:00Z,102540\n");
+        assert_eq!(
+            written,
+            "timestamp_utc,USD,GBP\n2024-09-06T08:30:00Z,102600,131000\n"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::storage::unix_now;
+
+/// Unix time of the last cycle that ended in a successful post (or a
+/// deliberate skip because nothing changed).
+pub type LastSuccess = Arc<AtomicU64>;
+
+pub fn mark_success(last_success: &LastSuccess) {
+    last_success.store(unix_now() as u64, Ordering::Relaxed);
+}
+
+#[derive(Clone)]
+struct HealthState {
+    last_success: LastSuccess,
+    max_age: Duration,
+}
+
+#[derive(Serialize)]
+struct HealthBody {
+    status: &'static str,
+    last_cycle_ago_secs: u64,
+}
+
+async fn health_handler(State(state): State<HealthState>) -> (StatusCode, Json<HealthBody>) {
+    let last = state.last_success.load(Ordering::Relaxed);
+    let ago = (unix_now() as u64).saturating_sub(last);
+    if ago > state.max_age.as_secs() {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthBody {
+                status: "stale",
+                last_cycle_ago_secs: ago,
+            }),
+        )
+    } else {
+        (
+            StatusCode::OK,
+            Json(HealthBody {
+                status: "ok",
+                last_cycle_ago_secs: ago,
+            }),
+        )
+    }
+}
+
+/// Serves `/health` on `port`; answers 503 once the last success is older
+/// than `max_age`. A bind failure is logged and the bot keeps running.
+pub async fn run_health_server(port: u16, last_success: LastSuccess, max_age: Duration) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(%addr, error = %e, "⚠️ سرور health بالا نیومد");
+            return;
+        }
+    };
+    info!(%addr, "🩺 health endpoint listening on /health");
+
+    let app = Router::new()
+        .route("/health", get(health_handler))
+        .with_state(HealthState {
+            last_success,
+            max_age,
+        });
+    if let Err(e) = axum::serve(listener, app).await {
+        warn!(error = %e, "⚠️ سرور health متوقف شد");
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{Datelike, NaiveDate};
+
+// روزهای پیش از هر ماه میلادی در سال غیرکبیسه
+const DAYS_BEFORE_MONTH: [i64; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+/// The Jalali (Persian calendar) year, month and day of a Gregorian date.
+/// Uses the 33-year arithmetic cycle, which matches the official calendar
+/// for the years this bot will see.
+pub fn to_jalali(date: NaiveDate) -> (i64, u32, u32) {
+    let (gy, gm, gd) = (
+        i64::from(date.year()),
+        date.month() as usize,
+        i64::from(date.day()),
+    );
+    // کبیسه‌ی میلادی از مارس به بعد حساب می‌شه
+    let gy2 = if gm > 2 { gy + 1 } else { gy };
+    let mut days = 355_666 + 365 * gy + (gy2 + 3) / 4 - (gy2 + 99) / 100
+        + (gy2 + 399) / 400
+        + gd
+        + DAYS_BEFORE_MONTH[gm - 1];
+
+    let mut jy = -1595 + 33 * (days / 12_053);
+    days %= 12_053;
+    jy += 4 * (days / 1461);
+    days %= 1461;
+    if days > 365 {
+        jy += (days - 1) / 365;
+        days = (days - 1) % 365;
+    }
+    // شش ماه اول ۳۱ روزه‌ان، بقیه ۳۰ (و اسفند ۲۹ یا ۳۰)
+    let (jm, jd) = if days < 186 {
+        (1 + days / 31, 1 + days % 31)
+    } else {
+        (7 + (days - 186) / 30, 1 + (days - 186) % 30)
+    };
+    (jy, jm as u32, jd as u32)
+}
+
+/// `1403/06/15`, with Latin digits.
+pub fn format_jalali(date: NaiveDate) -> String {
+    let (y, m, d) = to_jalali(date);
+    format!("{}/{:02}/{:02}", y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jalali(y: i32, m: u32, d: u32) -> (i64, u32, u32) {
+        to_jalali(NaiveDate::from_ymd_opt(y, m, d).unwrap())
+    }
+
+    #[test]
+    fn nowruz_1403() {
+        assert_eq!(jalali(2024, 3, 19), (1402, 12, 29));
+        assert_eq!(jalali(2024, 3, 20), (1403, 1, 1));
+    }
+
+    #[test]
+    fn leap_year_1403_ends_on_esfand_30() {
+        assert_eq!(jalali(2025, 3, 20), (1403, 12, 30));
+        assert_eq!(jalali(2025, 3, 21), (1404, 1, 1));
+    }
+
+    #[test]
+    fn month_lengths() {
+        // آخر شهریور (۳۱ روزه) و اول مهر (۳۰ روزه)
+        assert_eq!(jalali(2024, 9, 21), (1403, 6, 31));
+        assert_eq!(jalali(2024, 9, 22), (1403, 7, 1));
+        // کبیسه‌ی میلادی قبل از نوروز
+        assert_eq!(jalali(2024, 2, 29), (1402, 12, 10));
+    }
+
+    #[test]
+    fn format_pads_month_and_day() {
+        let date = NaiveDate::from_ymd_opt(2024, 9, 5).unwrap();
+        assert_eq!(format_jalali(date), "1403/06/15");
+    }
+}
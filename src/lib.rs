@@ -0,0 +1,31 @@
+pub mod admin;
+pub mod alerts;
+pub mod api;
+pub mod cache;
+pub mod chart;
+pub mod circuit_breaker;
+pub mod config;
+pub mod currency;
+pub mod discord;
+pub mod dlq;
+pub mod error;
+pub mod export;
+pub mod health;
+pub mod jalali;
+pub mod logging;
+pub mod message;
+pub mod metrics;
+pub mod mqtt;
+pub mod retry;
+pub mod sanity;
+pub mod slack;
+pub mod sources;
+pub mod state;
+pub mod storage;
+pub mod summary;
+pub mod targets;
+pub mod telegram;
+pub mod telegram_poller;
+pub mod template;
+pub mod trend;
+pub mod ws;
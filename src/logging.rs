@@ -0,0 +1,29 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::config::var;
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Installs the global tracing subscriber. `LOG_FORMAT` is `pretty` (default)
+/// or `json`; `LOG_LEVEL` (or the usual `RUST_LOG`) takes a level or a filter
+/// like `peybot_rust=debug`.
+pub fn init_logging() -> Result<(), String> {
+    let level = var("LOG_LEVEL")
+        .or_else(|_| var("RUST_LOG"))
+        .unwrap_or_else(|_| DEFAULT_LOG_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&level)
+        .map_err(|e| format!("Invalid LOG_LEVEL value '{}': {}", level, e))?;
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let installed = match var("LOG_FORMAT").as_deref() {
+        Err(_) | Ok("pretty") => builder.pretty().try_init(),
+        Ok("json") => builder.json().try_init(),
+        Ok(other) => {
+            return Err(format!(
+                "Invalid LOG_FORMAT '{}', expected 'pretty' or 'json'",
+                other
+            ));
+        }
+    };
+    installed.map_err(|e| format!("Failed to install logger: {}", e))
+}
@@ -1,204 +1,1312 @@
-use std::env;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::time::{Duration, Instant};
 
+use chrono::Utc;
 use dotenv::dotenv;
-use num_format::{Locale, ToFormattedString};
+use futures::TryFutureExt;
+use futures::future::join_all;
 use reqwest::Client;
-use scraper::{Html, Selector};
-use serde::Deserialize;
-use tokio::time::sleep;
-
-#[derive(Deserialize)]
-struct BtcTurkRes {
-    success: bool,
-    data: Vec<BtcTurkItem>,
-}
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc, watch};
+use tokio::time::{MissedTickBehavior, interval_at};
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
-#[derive(Deserialize)]
-struct BtcTurkItem {
-    last: f64,
-}
+use peybot_rust::admin::{DEFAULT_FAILURE_ALERT_AFTER, FailureTracker, send_admin_notices};
+use peybot_rust::alerts::{
+    AlertCooldown, DEFAULT_ALERT_COOLDOWN, RateMap, check_thresholds, format_alert,
+};
+use peybot_rust::api::run_http_server;
+use peybot_rust::cache::{DEFAULT_CACHE_EXPIRY_SECS, LineCache};
+use peybot_rust::circuit_breaker::{
+    CircuitBreaker, DEFAULT_CIRCUIT_FAILURES, DEFAULT_CIRCUIT_OPEN_SECS,
+};
+use peybot_rust::config::{
+    self, CurrencyConfig, DEFAULT_DB_PATH, DEFAULT_HEALTH_PORT, DEFAULT_METRICS_PORT,
+    DEFAULT_STATE_PATH, Group, LiraSource, PostMode, PriceUnit, read_admin_chat_id,
+    read_admin_user_ids, read_alert_chat_id, read_coingecko_ids, read_cycles, read_decimal_places,
+    read_discord_webhook_url, read_dry_run, read_env_proxies, read_export_csv, read_http_listen,
+    read_lira_source, read_max_jump_pct, read_message_template, read_mqtt_broker,
+    read_number_style, read_optional_secs, read_parse_mode, read_port, read_post_mode,
+    read_post_options, read_proxy, read_quiet_hours, read_rate_bounds, read_request_timeout,
+    read_retention_days, read_run_once, read_show_daily_range, read_signature_footer,
+    read_slack_webhook_url, read_sma_window, read_summary_chat_id, read_summary_time, read_targets,
+    read_thresholds, read_try_max_diff_pct, read_try_sources, read_update_interval,
+};
+use peybot_rust::currency::{Currency, Rial};
+use peybot_rust::discord::send_discord_message;
+use peybot_rust::dlq::{DeadLetterQueue, PendingMessage, run_dlq_drainer};
+use peybot_rust::error::{BotError, FetchError};
+use peybot_rust::export::CsvExport;
+use peybot_rust::health::{LastSuccess, mark_success, run_health_server};
+use peybot_rust::logging::init_logging;
+use peybot_rust::message::{
+    DEDUP_TOLERANCE_PCT, Message, ParseMode, PreviousRates, RateLine, Section, Unit, fmt_int,
+    fmt_pct, format_message, interval_label,
+};
+use peybot_rust::metrics::{Metrics, SharedMetrics, run_metrics_server};
+use peybot_rust::mqtt::{MQTT_CHANNEL_SIZE, run_mqtt_publisher};
+use peybot_rust::retry::{MAX_ATTEMPTS, RETRY_BASE_DELAY, retry_with_backoff};
+use peybot_rust::sanity::{SanityGuard, StalenessTracker, build_bounds};
+use peybot_rust::slack::{format_message_slack, send_slack_message};
+use peybot_rust::sources::{
+    CRYPTO_PAIRS, DEFAULT_BTCTURK_MAX_AGE_SECS, Quote, Rate, build_rate_sources, coingecko_coin,
+    fetch_crypto_usd,
+};
+use peybot_rust::state::{SharedState, StateStore};
+use peybot_rust::storage::{Storage, unix_now};
+use peybot_rust::summary::{DailySummary, SUMMARY_TZ, format_summary};
+use peybot_rust::targets::Target;
+use peybot_rust::telegram::{
+    SendOptions, edit_telegram_message, log_send_failure, publish, send_telegram_message,
+    setup_bot_metadata,
+};
+use peybot_rust::telegram_poller::{
+    BOT_COMMANDS, RateSnapshot, RefreshButton, RefreshRequest, SharedSnapshot, run_poller,
+};
+use peybot_rust::trend::TrendTracker;
+use peybot_rust::ws::RATE_UPDATES_BUFFER;
 
-fn fmt_int(n: i64) -> String {
-    n.to_formatted_string(&Locale::en)
-}
-
-fn round_up_to_i64(v: f64) -> i64 {
-    v.ceil() as i64
-}
+// Ctrl+C یا SIGTERM (مثلاً از docker stop)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "⚠️ گوش دادن به Ctrl+C ناموفق");
+            std::future::pending::<()>().await;
+        }
+    };
 
-async fn fetch_tgju_rate(client: &Client, url: &str) -> Result<i64, String> {
-    let resp = client
-        .get(url)
-        .header(
-            "User-Agent",
-            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/128.0",
-        )
-        .send()
-        .await
-        .map_err(|e| format!("Request error for {}: {}", url, e))?;
-
-    let body = resp
-        .text()
-        .await
-        .map_err(|e| format!("Read body error for {}: {}", url, e))?;
-
-    let doc = Html::parse_document(&body);
-    // selector used in your python code
-    let selector = Selector::parse(".top-mobile-block .block-last-change-percentage .price")
-        .map_err(|e| format!("Selector parse error: {}", e))?;
-
-    if let Some(elem) = doc.select(&selector).next() {
-        let raw = elem.text().collect::<Vec<_>>().join("").trim().to_string();
-        // temizle: ویرگول و فاصله‌ها رو حذف کنیم
-        let clean = raw
-            .replace(",", "")
-            .replace(" ", "")
-            .replace("\u{200c}", "");
-        match clean.parse::<i64>() {
-            Ok(v) => Ok(v),
-            Err(e) => Err(format!("Parse int error for '{}' : {}", clean, e)),
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "⚠️ گوش دادن به SIGTERM ناموفق");
+                std::future::pending::<()>().await;
+            }
         }
-    } else {
-        Err(format!("Selector not found on {}", url))
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
 
-async fn fetch_usdt_try(client: &Client, url: &str) -> Result<f64, String> {
-    let resp = client
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| format!("BTCTurk request error: {}", e))?;
-    let txt = resp
-        .text()
-        .await
-        .map_err(|e| format!("BTCTurk read body error: {}", e))?;
-
-    let parsed: Result<BtcTurkRes, _> = serde_json::from_str(&txt);
-    match parsed {
-        Ok(obj) => {
-            if obj.success && !obj.data.is_empty() {
-                Ok(obj.data[0].last)
-            } else {
-                Err("BTCTurk responded with success=false or empty data".to_string())
+const SHUTDOWN_NOTICE_TIMEOUT: Duration = Duration::from_secs(5);
+// کد خروج --once: ۰ یعنی پست شد یا لازم نبود
+const EXIT_SEND_FAILED: i32 = 1;
+const EXIT_NO_RATES: i32 = 2;
+
+const DEFAULT_FORCE_SEND_EVERY: u32 = 10;
+
+// اسم منبع‌هایی که در رجیستری نیستن، برای مدار، متریک و گزارش خطا
+const USDT_TRY: &str = "USDT_TRY";
+const COINGECKO: &str = "COINGECKO";
+
+// جای chat id در DRY_RUN وقتی هیچ کانالی تنظیم نشده
+const DRY_RUN_CHAT: &str = "dry-run";
+// فاصله‌ی بین ارسال به کانال‌های مختلف، به خاطر محدودیت نرخ تلگرام
+const TARGET_SEND_DELAY: Duration = Duration::from_millis(300);
+
+// DRY_RUN: پیام به جای تلگرام روی stdout چاپ می‌شه
+fn print_dry_run(chat_id: &str, text: &str) {
+    println!("--- DRY RUN → {} ---\n{}\n--- END ---\n", chat_id, text);
+}
+
+const SANA_TITLE: &str = "🏦 دلار سنا";
+
+// بخش سنا فقط وقتی نشون داده می‌شه که نرخ دلار آزاد و همه‌ی نرخ‌های سنا همین
+// چرخه اومده باشن؛ کنار هر کدوم فاصله‌ی دلار آزاد ازش به درصد میاد
+fn add_sana_spread(sections: &mut [Section]) {
+    let free_usd = sections
+        .iter()
+        .flat_map(|s| &s.lines)
+        .find(|l| l.currency == Currency::Usd && l.is_fresh())
+        .map(|l| l.value);
+    let Some(sana) = sections
+        .iter_mut()
+        .find(|s| s.title.as_deref() == Some(SANA_TITLE))
+    else {
+        return;
+    };
+    let complete = sana.lines.iter().all(RateLine::is_fresh);
+    match free_usd.filter(|_| complete) {
+        Some(usd) => {
+            for line in &mut sana.lines {
+                // fmt_pct با نرخ صفر چیزی برنمی‌گردونه
+                let pct = fmt_pct(usd - line.value, line.value);
+                if !pct.is_empty() {
+                    line.note = Some(format!("| آزاد{}", pct));
+                }
             }
         }
-        Err(e) => Err(format!("BTCTurk json parse error: {} / body: {}", e, txt)),
+        None => sana.lines.clear(),
     }
 }
 
-async fn send_telegram_message(client: &Client, bot_token: &str, chat_id: &str, text: &str) {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-    let params = [("chat_id", chat_id), ("text", text)];
-    match client.post(&url).form(&params).send().await {
-        Ok(resp) => {
-            let status = resp.status();
-            if status.is_success() {
-                println!("✅ پیام به تلگرام ارسال شد");
-            } else {
-                // چون resp در اینجا move می‌شه، متن رو جدا می‌خونیم و فقط status قبلاً ذخیره شده
-                match resp.text().await {
-                    Ok(body) => println!("⚠️ تلگرام پاسخ غیرموفق داد: {} / body: {}", status, body),
-                    Err(_) => println!("⚠️ تلگرام پاسخ غیرموفق داد: {}", status),
-                }
-            }
+// ستون‌های CSV به همون ترتیب پیام: ارزهای تنظیم‌شده که تومانی نشون داده
+// می‌شن، بعد لیر و ارزهای دیجیتال
+fn export_columns(currencies: &[CurrencyConfig], coingecko_ids: &[String]) -> Vec<String> {
+    let mut columns: Vec<String> = currencies
+        .iter()
+        .filter(|c| c.unit != PriceUnit::Usd && c.code != Currency::Try)
+        .map(|c| c.code.code().to_string())
+        .collect();
+    columns.push(Currency::Try.code().to_string());
+    columns.extend(CRYPTO_PAIRS.iter().map(|(code, ..)| code.to_string()));
+    columns.push(Currency::Usdt.code().to_string());
+    for id in coingecko_ids {
+        let (code, ..) = coingecko_coin(id);
+        if !columns.contains(&code) {
+            columns.push(code);
         }
-        Err(e) => println!("❌ خطا در ارسال به تلگرام: {}", e),
     }
+    columns
+}
+
+// نتیجه‌ی future همراه با مدتی که طول کشید، برای هیستوگرام metrics
+async fn timed<T>(fut: impl Future<Output = T>) -> (T, Duration) {
+    let started = Instant::now();
+    let out = fut.await;
+    (out, started.elapsed())
+}
+
+// تا مضرب بعدی بازه از ساعت یونیکس؛ با بازه‌ی ۶۰ ثانیه یعنی سر دقیقه
+fn until_aligned(interval: Duration) -> Duration {
+    let secs = interval.as_secs().max(1);
+    let rem = unix_now() as u64 % secs;
+    Duration::from_secs(if rem == 0 { secs } else { secs - rem })
 }
 
 #[tokio::main]
 async fn main() {
     dotenv().ok(); // load .env if exists
+    // قبل از لاگ، چون LOG_LEVEL و LOG_FORMAT هم می‌تونن از فایل بیان
+    let config_path = config::load_file_config().unwrap_or_else(|e| panic!("{}", e));
+    init_logging().unwrap_or_else(|e| panic!("{}", e));
+    if let Some(path) = &config_path {
+        info!(path = %path, "⚙️ config file loaded");
+    }
+
+    let dry_run = read_dry_run();
+    let run_once = read_run_once();
+    if dry_run {
+        info!("🧪 DRY RUN: پیام‌ها فقط چاپ می‌شن و به تلگرام چیزی فرستاده نمی‌شه");
+    }
+
+    // در DRY_RUN توکن و کانال لازم نیست
+    let bot_token = match config::var("BOT_TOKEN") {
+        Ok(token) => token,
+        Err(_) if dry_run => String::new(),
+        Err(_) => panic!("BOT_TOKEN not set (env var or bot_token in the config file)"),
+    };
+    let targets = match read_targets() {
+        Ok(targets) => targets,
+        Err(_) if dry_run => vec![Target::plain(DRY_RUN_CHAT)],
+        Err(e) => panic!("{}", e),
+    };
+    let chat_ids: Vec<String> = targets.iter().map(|t| t.chat_id.clone()).collect();
+    let admin_chat_id = read_admin_chat_id().filter(|_| !dry_run);
+    let discord_webhook = read_discord_webhook_url();
+    let slack_webhook = read_slack_webhook_url();
+    let thresholds = read_thresholds().unwrap_or_else(|e| panic!("{}", e));
+    // هشدار آستانه‌ها به ALERT_CHAT_ID، یا اگه نباشه به همون کانال‌ها
+    let alert_chats = match read_alert_chat_id() {
+        Some(id) => vec![id],
+        None => chat_ids.clone(),
+    };
+    let mut alert_cooldown = AlertCooldown::new(
+        read_optional_secs("ALERT_COOLDOWN_SECS")
+            .unwrap_or_else(|e| panic!("{}", e))
+            .unwrap_or(DEFAULT_ALERT_COOLDOWN),
+    );
+    let update_interval = read_update_interval().unwrap_or_else(|e| panic!("{}", e));
+    let request_timeout = read_request_timeout().unwrap_or_else(|e| panic!("{}", e));
+
+    let force_post_every =
+        read_optional_secs("FORCE_POST_EVERY_SECS").unwrap_or_else(|e| panic!("{}", e));
+    // بعد از این تعداد چرخه‌ی بدون تغییر، پست به هر حال می‌ره تا کانال ساکت نمونه
+    let force_send_every = read_cycles("FORCE_SEND_EVERY", DEFAULT_FORCE_SEND_EVERY)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let post_mode = read_post_mode().unwrap_or_else(|e| panic!("{}", e));
+    let try_sources = read_try_sources().unwrap_or_else(|e| panic!("{}", e));
+    let coingecko_ids = read_coingecko_ids().unwrap_or_else(|e| panic!("{}", e));
+    let btcturk_max_age = read_optional_secs("BTCTURK_MAX_AGE_SECS")
+        .unwrap_or_else(|e| panic!("{}", e))
+        .unwrap_or(Duration::from_secs(DEFAULT_BTCTURK_MAX_AGE_SECS));
+    let state_path = config::var("STATE_PATH").unwrap_or_else(|_| DEFAULT_STATE_PATH.to_string());
+    let store: SharedState = Arc::new(Mutex::new(StateStore::load(&state_path)));
+    let parse_mode = read_parse_mode().unwrap_or_else(|e| panic!("{}", e));
+    let post_options = read_post_options(parse_mode);
+    // هشدارها و خلاصه‌ی روزانه حتی با SILENT_POSTS اعلان می‌دن
+    let alert_options = SendOptions {
+        parse_mode: None,
+        ..post_options
+    }
+    .notifying();
+    let number_style = read_number_style().unwrap_or_else(|e| panic!("{}", e));
+    let signature_footer = read_signature_footer();
+    let message_template = read_message_template();
+    let currencies = config::load_currencies().unwrap_or_else(|e| panic!("{}", e));
+    let retention_days = read_retention_days().unwrap_or_else(|e| panic!("{}", e));
+    let max_jump_pct = read_max_jump_pct().unwrap_or_else(|e| panic!("{}", e));
+    let decimal_places = read_decimal_places().unwrap_or_else(|e| panic!("{}", e));
+    let rate_bounds = read_rate_bounds().unwrap_or_else(|e| panic!("{}", e));
+    let sma_window = read_sma_window().unwrap_or_else(|e| panic!("{}", e));
+    let stale_cycles = read_cycles("STALE_CYCLES", 3).unwrap_or_else(|e| panic!("{}", e));
+    let stale_alert_cycles =
+        read_cycles("STALE_ALERT_CYCLES", 10).unwrap_or_else(|e| panic!("{}", e));
+    let cache_expiry = read_optional_secs("CACHE_EXPIRY_SECS")
+        .unwrap_or_else(|e| panic!("{}", e))
+        .unwrap_or(Duration::from_secs(DEFAULT_CACHE_EXPIRY_SECS));
+    let circuit_failures = read_cycles("CIRCUIT_FAILURES", DEFAULT_CIRCUIT_FAILURES)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let circuit_open_for = read_optional_secs("CIRCUIT_OPEN_SECS")
+        .unwrap_or_else(|e| panic!("{}", e))
+        .unwrap_or(Duration::from_secs(DEFAULT_CIRCUIT_OPEN_SECS));
+    let failure_alert_after = read_cycles("FAILURE_ALERT_AFTER", DEFAULT_FAILURE_ALERT_AFTER)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let summary_time = read_summary_time().unwrap_or_else(|e| panic!("{}", e));
+    let lira_source = read_lira_source().unwrap_or_else(|e| panic!("{}", e));
+    let try_max_diff_pct = read_try_max_diff_pct().unwrap_or_else(|e| panic!("{}", e));
+    let quiet_hours = read_quiet_hours().unwrap_or_else(|e| panic!("{}", e));
+    let show_daily_range = read_show_daily_range();
+    let summary_chats = match read_summary_chat_id() {
+        Some(id) => vec![id],
+        None => chat_ids.clone(),
+    };
+
+    // DB_PATH، یا DATABASE_PATH قدیمی
+    let db_path = config::var("DB_PATH")
+        .or_else(|_| config::var("DATABASE_PATH"))
+        .unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+    let db = Arc::new(
+        Storage::open(&db_path)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to open database {}: {}", db_path, e)),
+    );
+    let mut csv_export = read_export_csv()
+        .map(|path| CsvExport::new(&path, export_columns(&currencies, &coingecko_ids)));
+    // انتشار MQTT تسک جدای خودش رو داره تا قطعی بروکر چرخه رو کند نکنه
+    let (mqtt, mqtt_publisher) = match read_mqtt_broker().unwrap_or_else(|e| panic!("{}", e)) {
+        Some(broker) => {
+            let (tx, rx) = mpsc::channel(MQTT_CHANNEL_SIZE);
+            (Some(tx), Some(tokio::spawn(run_mqtt_publisher(broker, rx))))
+        }
+        None => (None, None),
+    };
+    if let Some(days) = retention_days {
+        match db.prune_older_than(days).await {
+            Ok(n) => info!(rows = n, days, "🧹 ردیف‌های قدیمی پاک شد"),
+            Err(e) => warn!(error = %e, "⚠️ پاک‌سازی دیتابیس ناموفق"),
+        }
+    }
+    match db.get_last_n_rates("USD", 1).await {
+        Ok(records) => {
+            if let Some(r) = records.first() {
+                info!(
+                    currency = %r.currency,
+                    toman = r.value_toman,
+                    ago_secs = unix_now() - r.fetched_at,
+                    "💾 آخرین نرخ ذخیره‌شده"
+                );
+            }
+        }
+        Err(e) => warn!(error = %e, "⚠️ خواندن تاریخچه از دیتابیس ناموفق"),
+    }
+
+    // tgju مستقیم (یا با TGJU_PROXY)، تلگرام با TELEGRAM_PROXY؛ هر کدوم کلاینت خودشون رو دارن.
+    // HTTPS_PROXY/HTTP_PROXY بعد از اون‌ها میان، پس پروکسی مخصوص هر کلاینت اولویت داره
+    let env_proxies = read_env_proxies();
+    let build_client = |proxy_var: &str| {
+        let mut builder = Client::builder()
+            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/128.0")
+            .timeout(request_timeout)
+            // پروکسی‌ها فقط همین‌هایی‌ان که اینجا اضافه می‌شن و لاگ شدن
+            .no_proxy();
+        if let Some(proxy) = read_proxy(proxy_var).unwrap_or_else(|e| panic!("{}", e)) {
+            builder = builder.proxy(proxy);
+        }
+        for proxy in &env_proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder.build().expect("Failed to build client")
+    };
+    let client = build_client("TGJU_PROXY");
+    let tg_client = build_client("TELEGRAM_PROXY");
+
+    info!(
+        interval_secs = update_interval.as_secs(),
+        chats = %chat_ids.join(", "),
+        "▶️ peybot_rust started"
+    );
+
+    // آخرین مقادیری که واقعاً پست شدن، برای اینکه پیام تکراری نفرستیم
+    let rate_snapshot: SharedSnapshot = Arc::new(RwLock::new(RateSnapshot::default()));
+    let admin_user_ids = read_admin_user_ids().unwrap_or_else(|e| panic!("{}", e));
+    // فرستنده‌ی صف رو خود main هم نگه می‌داره تا recv بی‌پولر هم None نده
+    let (refresh_tx, mut refresh_rx) = mpsc::channel::<RefreshRequest>(1);
+    let mut channel_options = post_options;
+    // با --once پروسه وسط getUpdates تموم می‌شه و دستورهای گرفته‌شده بی‌جواب می‌مونن
+    if dry_run || run_once || config::var("DISABLE_COMMANDS").is_ok_and(|v| v == "1") {
+        info!("ℹ️ پاسخ به دستورات ربات غیرفعال است");
+        if !admin_user_ids.is_empty() {
+            warn!("⚠️ دکمه‌ی 🔄 بدون پاسخ به دستورات کار نمی‌کنه و نمایش داده نمی‌شه");
+        }
+    } else {
+        setup_bot_metadata(&tg_client, &bot_token, BOT_COMMANDS).await;
+        channel_options.refresh_button = !admin_user_ids.is_empty();
+        tokio::spawn(run_poller(
+            tg_client.clone(),
+            bot_token.clone(),
+            rate_snapshot.clone(),
+            store.clone(),
+            db.clone(),
+            RefreshButton {
+                admin_ids: admin_user_ids,
+                requests: refresh_tx.clone(),
+            },
+        ));
+    }
 
-    let bot_token = env::var("BOT_TOKEN").expect("BOT_TOKEN env var not set");
-    let chat_id = env::var("CHANNEL_ID").expect("CHANNEL_ID env var not set");
+    // اجرای یک‌باره (مثلاً از cron) پورت نمی‌گیره تا با نمونه‌ی همیشگی تداخل نکنه
+    let metrics: SharedMetrics = Arc::new(Metrics::new());
+    if run_once || config::var("DISABLE_METRICS").is_ok_and(|v| v == "1") {
+        info!("ℹ️ سرور metrics غیرفعال است");
+    } else {
+        let port =
+            read_port("METRICS_PORT", DEFAULT_METRICS_PORT).unwrap_or_else(|e| panic!("{}", e));
+        tokio::spawn(run_metrics_server(port, metrics.clone()));
+    }
+
+    // تا اولین پست، زمان شروع رو موفق حساب می‌کنیم که probe بلافاصله شکست نخوره
+    let last_success: LastSuccess = Arc::new(AtomicU64::new(unix_now() as u64));
+    if run_once || config::var("DISABLE_HEALTH").is_ok_and(|v| v == "1") {
+        info!("ℹ️ سرور health غیرفعال است");
+    } else {
+        let port =
+            read_port("HEALTH_PORT", DEFAULT_HEALTH_PORT).unwrap_or_else(|e| panic!("{}", e));
+        tokio::spawn(run_health_server(
+            port,
+            last_success.clone(),
+            update_interval * 2,
+        ));
+    }
 
-    let urls = vec![
-        ("USD", "https://www.tgju.org/profile/price_dollar_rl"),
-        ("EUR", "https://www.tgju.org/profile/price_eur"),
-        ("AED", "https://www.tgju.org/profile/price_aed"),
-        ("CNY", "https://www.tgju.org/profile/sana_sell_cny"),
-    ];
+    let (task_shutdown, task_shutdown_rx) = watch::channel(false);
+    // هر چرخه به کلاینت‌های /ws/rates هم فرستاده می‌شه
+    let (rate_updates, _) = broadcast::channel::<RateSnapshot>(RATE_UPDATES_BUFFER);
+    let http_server = read_http_listen()
+        .unwrap_or_else(|e| panic!("{}", e))
+        .filter(|_| !run_once)
+        .map(|addr| {
+            tokio::spawn(run_http_server(
+                addr,
+                rate_snapshot.clone(),
+                rate_updates.clone(),
+                last_success.clone(),
+                metrics.clone(),
+                update_interval * 3,
+                task_shutdown_rx,
+            ))
+        });
 
-    let btcturk_url = "https://api.btcturk.com/api/v2/ticker?pairSymbol=USDT_TRY";
+    // پیام‌هایی که به خاطر قطعی تلگرام نرفتن، هر ۳۰ ثانیه دوباره امتحان می‌شن؛
+    // با --once کسی نمی‌مونه که دوباره بفرسته و صف فقط در پایان گزارش می‌شه
+    let dlq = DeadLetterQueue::default();
+    let dlq_drainer = (!run_once).then(|| {
+        tokio::spawn(run_dlq_drainer(
+            tg_client.clone(),
+            bot_token.clone(),
+            dlq.clone(),
+            task_shutdown.subscribe(),
+        ))
+    });
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/128.0")
-        .build()
-        .expect("Failed to build client");
+    let rate_sources = build_rate_sources(&currencies, try_sources, btcturk_max_age)
+        .unwrap_or_else(|e| panic!("{}", e));
+    info!(sources = rate_sources.len(), "📦 rate sources registered");
+    // هر منبع مدار خودش رو داره؛ زنجیره‌ی لیر و CoinGecko هر کدوم یک منبع حساب می‌شن
+    let breakers: HashMap<&str, CircuitBreaker> = rate_sources
+        .iter()
+        .map(|s| s.name())
+        .chain([USDT_TRY, COINGECKO])
+        .map(|name| {
+            (
+                name,
+                CircuitBreaker::new(name, circuit_failures, circuit_open_for),
+            )
+        })
+        .collect();
+    let mut sanity = SanityGuard::new(build_bounds(&currencies, &rate_bounds), max_jump_pct);
+    let mut failures = FailureTracker::new(failure_alert_after);
+    let mut line_cache = LineCache::new(cache_expiry);
+    // آخرین دلار سالم به ریال، برای حساب لیر و رمزارزها وقتی دلار یک چرخه نیومد
+    let mut last_usd: Option<(Rial, Instant)> = None;
+    let mut staleness = StalenessTracker::new(stale_cycles, stale_alert_cycles);
+    let mut trend = sma_window.map(TrendTracker::new);
+    let mut last_posted;
+    let mut daily;
+    let mut last_post_at: Option<Instant>;
+    {
+        let mut store = store.lock().await;
+        last_posted = store.state.last_posted.clone();
+        daily = DailySummary::resume(
+            store.state.daily.take(),
+            Utc::now().with_timezone(&SUMMARY_TZ),
+        );
+        // زمان دیواری ذخیره‌شده به Instant همین اجرا برمی‌گرده
+        last_post_at = store.state.last_post_at.and_then(|at| {
+            let ago = (Utc::now() - at).to_std().unwrap_or_default();
+            Instant::now().checked_sub(ago)
+        });
+    }
+    let mut exit_code;
+    // مقدار هر ارز در چرخه‌ی قبل (نه آخرین پست) برای تشخیص عبور از آستانه‌ها
+    let mut prev_cycle = RateMap::new();
+    // چرخه‌هایی که پشت سر هم به خاطر بدون تغییر بودن پست نشدن
+    let mut skipped_posts: u32 = 0;
+    // لیر مستقیم و محاسبه‌شده در چرخه‌ی قبل با هم نمی‌خوندن
+    let mut lira_mismatch = false;
+    let mut cycle_no: u64 = 0;
+    // 🔄 زده‌شده‌ای که چرخه‌ی بعد باید جوابش رو بده
+    let mut refresh: Option<RefreshRequest> = None;
 
-    println!("▶️ peybot_rust started. Updating every 60 seconds...");
+    // چرخه‌ی اول همین الان اجرا می‌شه؛ بعدی‌ها روی تیک‌های ثابت، هر قدر هم fetch طول بکشه.
+    // تیکی که از دست رفته جبران نمی‌شه تا چند پست پشت سر هم نره
+    let first_tick = if config::var("ALIGN_INTERVAL").is_ok_and(|v| v == "1") {
+        until_aligned(update_interval)
+    } else {
+        update_interval
+    };
+    let mut ticker = interval_at(tokio::time::Instant::now() + first_tick, update_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    // همین اول spawn می‌شه تا سیگنالی که وسط چرخه برسه هم گرفته بشه
+    let mut shutdown = tokio::spawn(shutdown_signal());
 
     loop {
-        // collect rates
-        let mut rates: std::collections::HashMap<&str, i64> = std::collections::HashMap::new();
+        cycle_no += 1;
+        let cycle_span = info_span!("cycle", n = cycle_no);
+        exit_code = 0;
+        // گزارش‌های ادمین این چرخه، آخر چرخه یکجا فرستاده می‌شن
+        let mut admin_notices: Vec<String> = Vec::new();
+        'cycle: {
+            // همه‌ی منابع (و زنجیره‌ی USDT/TRY) همزمان درخواست می‌شن
+            let cycle_start = Instant::now();
+            let client_ref = &client;
+            let breakers_ref = &breakers;
+            let source_futs = rate_sources.iter().map(|s| async move {
+                let started = Instant::now();
+                let result = breakers_ref[s.name()]
+                    .call(|| {
+                        retry_with_backoff(
+                            || s.fetch(client_ref).map_err(BotError::from),
+                            MAX_ATTEMPTS,
+                            RETRY_BASE_DELAY,
+                        )
+                    })
+                    .await;
+                let took = started.elapsed();
+                debug!(
+                    source = s.name(),
+                    ok = result.is_ok(),
+                    duration_ms = took.as_millis() as u64,
+                    "source fetched"
+                );
+                (s.name(), result, took)
+            });
+            let gecko_fut = breakers[COINGECKO].call(|| {
+                retry_with_backoff(
+                    || fetch_crypto_usd(client_ref, &coingecko_ids).map_err(BotError::from),
+                    MAX_ATTEMPTS,
+                    RETRY_BASE_DELAY,
+                )
+            });
+            let tr_fut = breakers[USDT_TRY].call(|| rate_sources.fetch_usdt_try(&client));
+            let (results, (tr_result, tr_took), (gecko_result, gecko_took)) =
+                async { tokio::join!(join_all(source_futs), timed(tr_fut), timed(gecko_fut)) }
+                    .instrument(cycle_span.clone())
+                    .await;
+            info!(
+                cycle = cycle_no,
+                duration_ms = cycle_start.elapsed().as_millis() as u64,
+                "⏱ fetch cycle done"
+            );
+
+            let coingecko_prices = if coingecko_ids.is_empty() {
+                HashMap::new()
+            } else if let Some(e) = gecko_result.as_ref().err().filter(|e| e.is_circuit_open()) {
+                debug!(error = %e, "⏭ CoinGecko skipped");
+                HashMap::new()
+            } else {
+                metrics.record_fetch(COINGECKO, gecko_result.is_ok(), gecko_took);
+                match gecko_result {
+                    Ok(prices) => {
+                        admin_notices.extend(failures.success(COINGECKO));
+                        prices
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "⚠️ دریافت قیمت از CoinGecko ناموفق");
+                        admin_notices.extend(failures.failure(COINGECKO, &e.to_string(), false));
+                        HashMap::new()
+                    }
+                }
+            };
+
+            admin_notices.extend(match &tr_result {
+                Ok(_) => failures.success(USDT_TRY),
+                Err(e) if e.is_circuit_open() => None,
+                Err(e) => failures.failure(USDT_TRY, &e.to_string(), false),
+            });
+            if !tr_result.as_ref().is_err_and(|e| e.is_circuit_open()) {
+                metrics.record_fetch(USDT_TRY, tr_result.is_ok(), tr_took);
+            }
+
+            // collect rates
+            let mut fetched: HashMap<Currency, Rate> = HashMap::new();
+            for (name, result, took) in results {
+                // منبعی که مدار قطعش کرده این چرخه اصلاً درخواست نشده؛ کش جاش رو پر می‌کنه
+                if let Some(e) = result.as_ref().err().filter(|e| e.is_circuit_open()) {
+                    debug!(currency = %name, error = %e, "⏭ skipped");
+                    continue;
+                }
+                metrics.record_fetch(name, result.is_ok(), took);
+                match result {
+                    Ok(rate) => {
+                        info!(currency = %name, rate = %rate, "fetched");
+                        admin_notices.extend(failures.success(name));
+                        fetched.insert(rate.currency.clone(), rate);
+                    }
+                    Err(e) => {
+                        // این‌ها یعنی احتمالاً قالب صفحه‌ی tgju عوض شده، نه قطعی شبکه
+                        let structural = matches!(
+                            e.fetch_error(),
+                            Some(
+                                FetchError::SelectorNotFound { .. }
+                                    | FetchError::ParseInt { .. }
+                                    | FetchError::ParseFloat { .. }
+                                    | FetchError::OutOfRange { .. },
+                            )
+                        );
+                        if structural {
+                            error!(currency = %name, error = %e, "🚨 ساختار صفحه‌ی tgju تغییر کرده؟");
+                        } else {
+                            warn!(currency = %name, error = %e, "⚠️ fetch failed");
+                        }
+                        admin_notices.extend(failures.failure(name, &e.to_string(), structural));
+                    }
+                }
+            }
+
+            let missing: Vec<&str> = currencies
+                .iter()
+                .filter(|c| c.required && !fetched.contains_key(&c.code))
+                .map(|c| c.code.code())
+                .collect();
+            if !missing.is_empty() {
+                warn!(
+                    missing = %missing.join(", "),
+                    retry_in_secs = update_interval.as_secs(),
+                    "⚠️ نرخ‌های ضروری پیدا نشد"
+                );
+                exit_code = EXIT_NO_RATES;
+                break 'cycle;
+            }
 
-        for (name, url) in &urls {
-            match fetch_tgju_rate(&client, url).await {
-                Ok(v) => {
-                    rates.insert(name, v);
-                    println!("{} = {}", name, fmt_int(v));
+            // فقط وقتی هیچ منبعی جواب نداده پست نمی‌ره؛ بقیه‌ی حالت‌ها با هر چی اومده پست می‌شه
+            if fetched.is_empty() && tr_result.is_err() && coingecko_prices.is_empty() {
+                warn!(
+                    retry_in_secs = update_interval.as_secs(),
+                    "⚠️ هیچ نرخی دریافت نشد"
+                );
+                exit_code = EXIT_NO_RATES;
+                break 'cycle;
+            }
+
+            // بدون دلار، خط‌هایی که از قیمت دلاری حساب می‌شن (لیر، رمزارزها، کالاها) با
+            // آخرین دلار سالم (تا CACHE_EXPIRY_SECS) حساب می‌شن و ⏳ می‌خورن، بعدش حذف می‌شن
+            let mut usd_riyal = fetched.get(&Currency::Usd).and_then(Rate::rial);
+            let mut usd_age = None;
+            match (usd_riyal, last_usd) {
+                (Some(riyal), _) => last_usd = Some((riyal, Instant::now())),
+                (None, Some((riyal, at))) if at.elapsed() <= cache_expiry => {
+                    warn!(
+                        age_secs = at.elapsed().as_secs(),
+                        "⏳ نرخ دلار پیدا نشد، از آخرین مقدار سالم استفاده می‌شه"
+                    );
+                    usd_riyal = Some(riyal);
+                    usd_age = Some(at.elapsed().as_secs());
                 }
+                (None, _) => warn!("⚠️ نرخ دلار پیدا نشد، خط‌های وابسته به دلار حذف می‌شن"),
+            }
+
+            // btcturk
+            let try_source = match &tr_result {
+                Ok((_, source)) => Some(*source),
                 Err(e) => {
-                    println!("⚠️ دریافت {} ناموفق: {}", name, e);
+                    warn!(error = %e, "⚠️ هیچ منبعی برای USDT/TRY جواب نداد");
+                    None
+                }
+            };
+
+            // lira -> toman: ریال هر دلار تقسیم بر لیر هر دلار
+            // نبودن لیر فقط خطش رو حذف می‌کنه؛ کش آخرین مقدارش رو با علامت نشون می‌ده
+            let rial_per_lira = match (usd_riyal, &tr_result) {
+                (Some(usd_riyal), Ok((rate_tr, _))) => Some(usd_riyal.per(*rate_tr)),
+                _ => None,
+            };
+            // لیر مستقیم tgju با لیر محاسبه‌شده مقایسه می‌شه؛ اختلاف زیاد یعنی یکی از
+            // منبع‌ها قدیمی مونده. فقط یکی از دو تا در پیام میاد
+            let direct_lira = fetched.get(&Currency::Try).and_then(Rate::rial);
+            let lira = match (direct_lira, rial_per_lira) {
+                (Some(direct), Some(derived)) => {
+                    let diff_pct = (direct.0 - derived.0).abs() as f64 / derived.0 as f64 * 100.0;
+                    let mismatch = diff_pct > try_max_diff_pct;
+                    if mismatch {
+                        warn!(
+                            direct = direct.toman(),
+                            derived = derived.toman(),
+                            diff_pct = format!("{:.1}", diff_pct),
+                            "⚠️ اختلاف لیر مستقیم tgju و لیر محاسبه‌شده"
+                        );
+                    } else {
+                        debug!(diff_pct = format!("{:.1}", diff_pct), "lira cross-check ok");
+                    }
+                    // ادمین فقط شروع و پایان اختلاف رو می‌شنوه، نه هر چرخه
+                    if mismatch != lira_mismatch {
+                        admin_notices.push(if mismatch {
+                            format!(
+                                "🇹🇷 لیر مستقیم tgju ({}) و لیر محاسبه‌شده ({}) {:.1}٪ اختلاف دارند",
+                                fmt_int(direct.toman()),
+                                fmt_int(derived.toman()),
+                                diff_pct
+                            )
+                        } else {
+                            "🇹🇷 لیر مستقیم و محاسبه‌شده دوباره هم‌خوان‌اند".to_string()
+                        });
+                        lira_mismatch = mismatch;
+                    }
+                    match lira_source {
+                        LiraSource::Tgju => Some((direct, LiraSource::Tgju)),
+                        LiraSource::Derived => Some((derived, LiraSource::Derived)),
+                    }
+                }
+                (Some(direct), None) => {
+                    info!("⏭ lira cross-check skipped, only tgju's direct rate available");
+                    Some((direct, LiraSource::Tgju))
+                }
+                (None, Some(derived)) => {
+                    info!("⏭ lira cross-check skipped, only the derived rate available");
+                    Some((derived, LiraSource::Derived))
                 }
+                (None, None) => None,
+            };
+
+            // رمزارزها اختیاری‌ان؛ نبودشون جلوی پست رو نمی‌گیره
+            let crypto_usd =
+                |currency: &Currency| Some((usd_riyal?, fetched.get(currency)?.usd()?));
+            // تتر از نوبیتکس هم اختیاریه
+            let tether = fetched.get(&Currency::Usdt).and_then(Rate::rial);
+
+            // مقدار قبلی (به تومان) برای فلش تغییرات
+            let prev = |currency: &Currency| last_posted.get(currency);
+
+            // همه‌ی خط‌های تومانی با Rial::toman گرد می‌شن؛ با RATE_<CODE>_DECIMAL_PLACES
+            // به جاش `price` با اعشار نشون داده می‌شه (مثلاً دینار عراق)
+            let toman_line = |currency: &Currency, emoji: &str, name: &str, price: Rial| {
+                let places = decimal_places.get(currency.code());
+                let line = RateLine::new(
+                    currency.clone(),
+                    emoji,
+                    name,
+                    price.toman_scaled(places),
+                    prev(currency),
+                );
+                match places {
+                    0 => line,
+                    places => line.with_unit(Unit::TomanDecimals(places)),
+                }
+            };
+
+            // نرخ‌های ریالی به تومان تبدیل می‌شن، دلاری‌ها همون‌طور می‌مونن
+            // مگر unit = "usd_toman" که با نرخ دلار تومانی می‌شن
+            let tgju_line = |c: &CurrencyConfig| {
+                let rate = fetched.get(&c.code)?;
+                let line = match rate.quote {
+                    Quote::Usd(usd) if c.unit == PriceUnit::UsdToman => {
+                        toman_line(&c.code, &c.emoji, &c.name, usd_riyal?.times(usd))
+                            .with_cache_age(usd_age)
+                    }
+                    Quote::Usd(usd) => {
+                        let cents = (usd * 100.0).round() as i64;
+                        RateLine::new(c.code.clone(), &c.emoji, &c.name, cents, prev(&c.code))
+                            .with_unit(Unit::UsdCents)
+                    }
+                    Quote::Rial(price) => toman_line(&c.code, &c.emoji, &c.name, price),
+                };
+                Some(line)
+            };
+            // لیر فقط یک خط داره، همون خط مقایسه‌شده‌ی پایین؛ ورودی TRY در تنظیمات
+            // فقط اسم و ایموجی‌اش رو می‌ده
+            let group_lines = |group: Group| -> Vec<RateLine> {
+                currencies
+                    .iter()
+                    .filter(|c| c.group == group && c.code != Currency::Try)
+                    .filter_map(tgju_line)
+                    .collect()
+            };
+            let forex = group_lines(Group::Forex);
+            let gold = group_lines(Group::Gold);
+            let commodities = group_lines(Group::Commodity);
+            // اگه یکی از نرخ‌های سنا نیومده باشه بقیه‌اش هم نشون داده نمی‌شه
+            let mut sana = group_lines(Group::Sana);
+            if sana.len() < currencies.iter().filter(|c| c.group == Group::Sana).count() {
+                sana.clear();
             }
-        }
 
-        // need USD at least
-        if !rates.contains_key("USD") {
-            println!("⚠️ نرخ دلار پیدا نشد — منتظر 60 ثانیه...");
-            sleep(Duration::from_secs(60)).await;
-            continue;
-        }
+            let mut derived = Vec::new();
+            if let Some((price, source)) = lira {
+                let lira = Currency::Try;
+                let (emoji, name) = currencies
+                    .iter()
+                    .find(|c| c.code == lira)
+                    .map_or((lira.emoji(), lira.name()), |c| {
+                        (c.emoji.as_str(), c.name.as_str())
+                    });
+                derived.push(match source {
+                    LiraSource::Tgju => toman_line(&lira, emoji, name, price),
+                    LiraSource::Derived => {
+                        toman_line(&lira, emoji, name, price).with_cache_age(usd_age)
+                    }
+                });
+            }
+            for (code, name, emoji, _) in CRYPTO_PAIRS {
+                let currency = Currency::from(*code);
+                if let Some((usd_riyal, usd)) = crypto_usd(&currency) {
+                    derived.push(
+                        toman_line(&currency, emoji, name, usd_riyal.times(usd))
+                            .with_cache_age(usd_age),
+                    );
+                }
+            }
+            if let Some(price) = tether {
+                let usdt = Currency::Usdt;
+                derived.push(toman_line(&usdt, usdt.emoji(), usdt.name(), price));
+            }
+            // CoinGecko ارزهای تازه اضافه می‌کنه و جای منبعی که جواب نداده رو پر می‌کنه
+            for id in &coingecko_ids {
+                let (code, name, emoji) = coingecko_coin(id);
+                let currency = Currency::from(code);
+                if derived.iter().any(|l| l.currency == currency) {
+                    continue;
+                }
+                if let (Some(&usd), Some(usd_riyal)) = (coingecko_prices.get(id), usd_riyal) {
+                    derived.push(
+                        toman_line(&currency, &emoji, &name, usd_riyal.times(usd))
+                            .with_cache_age(usd_age),
+                    );
+                }
+            }
 
-        // btcturk
-        let rate_tr = match fetch_usdt_try(&client, btcturk_url).await {
-            Ok(v) => v,
-            Err(e) => {
-                println!("⚠️ خطا در دریافت USDT_TRY: {}", e);
-                sleep(Duration::from_secs(60)).await;
-                continue;
+            let mut footer = format!("🔄 به‌روزرسانی هر {}", interval_label(update_interval));
+            // فقط وقتی منبع جایگزین استفاده شده اسمش رو می‌آریم
+            let lira_derived = matches!(lira, Some((_, LiraSource::Derived)));
+            if let Some(source) =
+                try_source.filter(|s| lira_derived && *s != rate_sources.try_chain()[0])
+            {
+                footer.push_str(&format!("\n🇹🇷 نرخ لیر از {}", source.label()));
+            }
+            let mut message = Message {
+                sections: vec![
+                    Section::new(forex),
+                    Section::titled(SANA_TITLE, sana),
+                    Section::titled("🪙 طلا و سکه", gold),
+                    Section::titled("🛢️ کالاها", commodities),
+                    Section::new(derived),
+                ],
+                footer,
+                number_style,
+                posted_at: Utc::now().with_timezone(&SUMMARY_TZ),
+            };
+            // مقدارهای مشکوک با آخرین مقدار سالم (با علامت 🕐) عوض می‌شن
+            let rejected = sanity.apply(&mut message.sections);
+            if !rejected.is_empty() {
+                let report: Vec<String> = rejected
+                    .iter()
+                    .map(|(code, reason)| format!("{}: {}", code, reason))
+                    .collect();
+                admin_notices.push(format!("🚨 نرخ‌های مشکوک رد شد:\n{}", report.join("\n")));
             }
-        };
 
-        // compute lira -> toman logic: (riyal / rate_tr / 10)
-        let usd_riyal = *rates.get("USD").unwrap() as f64;
-        let toman_per_lira = usd_riyal / rate_tr / 10.0;
-        let toman_per_lira_i64 = round_up_to_i64(toman_per_lira);
+            // ارزی که این چرخه نیومد با آخرین مقدارش (تا CACHE_EXPIRY_SECS) نشون داده می‌شه
+            line_cache.apply(&mut message.sections);
 
-        // build message (فارسی)
-        // build message (فارسی)
-        let mut text = String::from("📊 نرخ لحظه‌ای ارز (به تومان):\n\n");
+            add_sana_spread(&mut message.sections);
 
-        // همه نرخ‌ها رو از ریال به تومان تبدیل کن (تقسیم بر 10)
-        if let Some(v) = rates.get("USD") {
-            text.push_str(&format!("💵 دلار: {} تومان\n", fmt_int(v / 10)));
-        }
-        if let Some(v) = rates.get("EUR") {
-            text.push_str(&format!("💶 یورو: {} تومان\n", fmt_int(v / 10)));
+            daily.record(Utc::now().with_timezone(&SUMMARY_TZ), message.lines());
+            store.lock().await.state.daily = Some(daily.clone());
+            if show_daily_range {
+                let codes: Vec<&str> = message.lines().map(|l| l.currency.code()).collect();
+                if let Some(range) = daily.range_footer(number_style, &codes) {
+                    message.footer = format!("{}\n\n{}", range, message.footer);
+                }
+            }
+
+            let fresh_lines = || message.lines().filter(|l| l.is_fresh());
+            let rates: RateMap = fresh_lines()
+                .map(|l| (l.currency.clone(), l.unit.whole(l.value)))
+                .collect();
+            for event in check_thresholds(&rates, &prev_cycle, &thresholds) {
+                if !alert_cooldown.allow(&event) {
+                    info!(currency = %event.currency, level = event.level, "🔕 threshold crossed again within cooldown");
+                    continue;
+                }
+                let unit = fresh_lines()
+                    .find(|l| l.currency == event.currency)
+                    .map_or(Unit::Toman, |l| l.unit.base());
+                let text = format_alert(&event, unit);
+                info!(currency = %event.currency, level = event.level, current = event.current, "🔔 threshold crossed");
+                for alert_chat in &alert_chats {
+                    if dry_run {
+                        print_dry_run(alert_chat, &text);
+                        continue;
+                    }
+                    let sent = retry_with_backoff(
+                        || {
+                            send_telegram_message(
+                                &tg_client,
+                                &bot_token,
+                                alert_chat,
+                                &text,
+                                alert_options,
+                            )
+                        },
+                        MAX_ATTEMPTS,
+                        RETRY_BASE_DELAY,
+                    )
+                    .await;
+                    if let Err(e) = sent {
+                        log_send_failure(alert_chat, &e);
+                        if e.is_transient() {
+                            dlq.push(PendingMessage::new(alert_chat, &text, alert_options));
+                        }
+                    }
+                }
+            }
+            // مقدارهای قدیمی جایگزین نمی‌شن تا بعد از برگشتن ارز عبور از آستانه گم نشه
+            prev_cycle.extend(rates);
+
+            // همه‌ی نرخ‌های تومانی این چرخه در تاریخچه، به جز مقدارهای قدیمی
+            let rows: Vec<(&str, i64)> = message
+                .lines()
+                .filter(|l| l.unit.base() == Unit::Toman && l.is_fresh())
+                .map(|l| (l.currency.code(), l.unit.whole(l.value)))
+                .collect();
+            // اجرای آزمایشی تاریخچه‌ی واقعی رو آلوده نمی‌کنه
+            if !dry_run && let Err(e) = db.insert_rates(unix_now(), &rows).await {
+                warn!(error = %e, "⚠️ ذخیره‌ی نرخ‌ها در دیتابیس ناموفق");
+            }
+            if !dry_run
+                && let Some(export) = &mut csv_export
+                && let Err(e) = export.append(Utc::now(), &rows).await
+            {
+                warn!(error = %e, "⚠️ نوشتن فایل CSV ناموفق");
+            }
+            if !dry_run && let Some(mqtt) = &mqtt {
+                let batch = rows.iter().map(|(code, toman)| (code.to_string(), *toman));
+                if mqtt.try_send(batch.collect()).is_err() {
+                    warn!("⚠️ صف MQTT پر است، این چرخه منتشر نشد");
+                }
+            }
+            for (code, toman) in &rows {
+                metrics.set_rate(code, *toman);
+            }
+
+            // نرخی که چند چرخه دقیقاً ثابت مونده احتمالاً از صفحه‌ی کش‌شده اومده؛
+            // فقط در پیام علامت می‌خوره و در تاریخچه و خلاصه همون مقدار واقعی حساب می‌شه
+            // روند قبل از علامت‌گذاری نرخ‌های ثابت، تا اون‌ها هم در میانگین بیان
+            if let Some(trend) = &mut trend {
+                trend.apply(&mut message.sections);
+            }
+            let frozen = staleness.apply(&mut message.sections);
+            if !frozen.is_empty() {
+                admin_notices.push(format!(
+                    "🕐 این نرخ‌ها {} چرخه است تغییر نکرده‌اند: {}",
+                    stale_alert_cycles,
+                    frozen.join(", ")
+                ));
+            }
+
+            let snapshot = RateSnapshot {
+                lines: message.lines().cloned().collect(),
+                taken_at: Some(Utc::now()),
+            };
+            *rate_snapshot.write().await = snapshot.clone();
+            // بدون کلاینت وصل، send خطا می‌ده که مهم نیست
+            let _ = rate_updates.send(snapshot);
+
+            // مقادیر مشتق‌شده هم در مقایسه حساب می‌شن، نه فقط نرخ‌های خام tgju
+            let values = PreviousRates::from_lines(message.lines());
+
+            let render_post = |target: &Target| {
+                let view = target.view(&message);
+                let mut text = match target.template.as_ref().or(message_template.as_ref()) {
+                    Some(template) => template.render(parse_mode, &view),
+                    None => format_message(parse_mode, &view),
+                };
+                if let Some(footer) = &signature_footer {
+                    text.push_str("\n\n");
+                    text.push_str(&parse_mode.text(footer));
+                }
+                text
+            };
+
+            // دکمه‌ی 🔄: فقط همون پیام با نرخ‌های این چرخه ویرایش می‌شه؛
+            // بقیه‌ی کانال‌ها و مقایسه با آخرین پست دست نمی‌خورن
+            if let Some(request) = refresh.take() {
+                let plain = Target::plain(&request.chat_id);
+                let target = targets
+                    .iter()
+                    .find(|t| t.is_chat(&request.chat_id, request.username.as_deref()))
+                    .unwrap_or(&plain);
+                let text = render_post(target);
+                let edited = retry_with_backoff(
+                    || {
+                        edit_telegram_message(
+                            &tg_client,
+                            &bot_token,
+                            &request.chat_id,
+                            request.message_id,
+                            &text,
+                            channel_options,
+                        )
+                    },
+                    MAX_ATTEMPTS,
+                    RETRY_BASE_DELAY,
+                )
+                .instrument(cycle_span.clone())
+                .await;
+                metrics.record_send(edited.is_ok());
+                match edited {
+                    Ok(()) => info!(chat_id = %request.chat_id, "🔄 پیام به‌روز شد"),
+                    Err(e) => log_send_failure(&request.chat_id, &e),
+                }
+                mark_success(&last_success);
+                break 'cycle;
+            }
+
+            // ساعت خلوت هر چرخه از نو حساب می‌شه، پس عبور از مرزش ری‌استارت نمی‌خواد؛
+            // تاریخچه و هشدارها بالاتر انجام شدن و فقط پست عادی عقب می‌افته
+            if let Some(quiet) = &quiet_hours
+                && quiet.contains(Utc::now().with_timezone(&SUMMARY_TZ).time())
+                && !quiet.post_due(last_post_at)
+            {
+                info!("🌙 quiet hours, skipping post");
+                mark_success(&last_success);
+                if let Err(e) = store.lock().await.save() {
+                    warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                }
+                break 'cycle;
+            }
+
+            let unchanged =
+                !last_posted.is_empty() && !values.changed_from(&last_posted, DEDUP_TOLERANCE_PCT);
+            let heartbeat_due = match (force_post_every, last_post_at) {
+                (Some(every), Some(at)) => at.elapsed() >= every,
+                _ => false,
+            } || skipped_posts >= force_send_every;
+            if unchanged && !heartbeat_due {
+                info!(skipped = skipped_posts + 1, "⏸ no change, skipping");
+                skipped_posts += 1;
+                mark_success(&last_success);
+                if let Err(e) = store.lock().await.save() {
+                    warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                }
+                break 'cycle;
+            }
+            skipped_posts = 0;
+
+            // send — شکست یک کانال جلوی بقیه رو نمی‌گیره
+            let mut any_sent = false;
+            for (i, target) in targets.iter().enumerate() {
+                if i > 0 && !dry_run {
+                    tokio::time::sleep(TARGET_SEND_DELAY).await;
+                }
+                let chat_id = &target.chat_id;
+                let text = render_post(target);
+                if dry_run {
+                    print_dry_run(chat_id, &text);
+                    any_sent = true;
+                    continue;
+                }
+                // قفل وضعیت فقط برای خوندن و نوشتن شناسه گرفته می‌شه، نه در طول retryها
+                let message_id = store.lock().await.state.message_ids.get(chat_id).copied();
+                let sent = publish(
+                    &tg_client,
+                    &bot_token,
+                    chat_id,
+                    &text,
+                    channel_options,
+                    post_mode,
+                    message_id,
+                )
+                .instrument(cycle_span.clone())
+                .await;
+                metrics.record_send(sent.is_ok());
+                let key = format!("ارسال به {}", chat_id);
+                match sent {
+                    Ok(new_id) => {
+                        if let Some(new_id) = new_id.filter(|id| Some(*id) != message_id) {
+                            let mut store = store.lock().await;
+                            store.state.message_ids.insert(chat_id.clone(), new_id);
+                            if let Err(e) = store.save() {
+                                warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                            }
+                        }
+                        any_sent = true;
+                        info!(chat_id = %chat_id, "✅ پیام به تلگرام ارسال شد");
+                        admin_notices.extend(failures.success(&key));
+                    }
+                    Err(e) => {
+                        log_send_failure(chat_id, &e);
+                        admin_notices.extend(failures.failure(&key, &e.to_string(), false));
+                        // در حالت edit چرخه‌ی بعد خودش پیام رو به‌روز می‌کنه
+                        if post_mode == PostMode::Send && e.is_transient() {
+                            dlq.push(PendingMessage::new(chat_id, &text, channel_options));
+                        }
+                    }
+                }
+            }
+
+            // مشترک‌های /subscribe پست پیش‌فرض رو بدون دکمه‌ی 🔄 می‌گیرن؛
+            // شکستشون روی وضعیت پست کانال اثری نداره
+            let subscribers = store.lock().await.state.subscribers.clone();
+            if !subscribers.is_empty() {
+                let text = render_post(&Target::plain(""));
+                let options = SendOptions {
+                    refresh_button: false,
+                    ..channel_options
+                };
+                for &subscriber in &subscribers {
+                    let chat_id = subscriber.to_string();
+                    if dry_run {
+                        print_dry_run(&chat_id, &text);
+                        continue;
+                    }
+                    tokio::time::sleep(TARGET_SEND_DELAY).await;
+                    let sent =
+                        send_telegram_message(&tg_client, &bot_token, &chat_id, &text, options)
+                            .instrument(cycle_span.clone())
+                            .await;
+                    match sent {
+                        // کسی که ربات رو بلاک کرده دیگه هیچ پستی نمی‌گیره
+                        Err(e) if e.is_blocked_by_user() => {
+                            info!(chat_id = %chat_id, "📭 مشترک ربات رو بلاک کرده، حذف شد");
+                            let mut store = store.lock().await;
+                            store.state.subscribers.retain(|id| *id != subscriber);
+                            if let Err(e) = store.save() {
+                                warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                            }
+                        }
+                        Err(e) => log_send_failure(&chat_id, &e),
+                        Ok(_) => {}
+                    }
+                }
+            }
+
+            // Discord فقط یک نسخه‌ی متنی ساده می‌گیره و شکستش روی تلگرام اثری نداره
+            if let Some(webhook) = &discord_webhook {
+                let mut text = match &message_template {
+                    Some(template) => template.render(ParseMode::Plain, &message),
+                    None => format_message(ParseMode::Plain, &message),
+                };
+                if let Some(footer) = &signature_footer {
+                    text.push_str("\n\n");
+                    text.push_str(footer);
+                }
+                if dry_run {
+                    print_dry_run("discord", &text);
+                } else {
+                    let sent = retry_with_backoff(
+                        || send_discord_message(&tg_client, webhook, &text),
+                        MAX_ATTEMPTS,
+                        RETRY_BASE_DELAY,
+                    )
+                    .instrument(cycle_span.clone())
+                    .await;
+                    match sent {
+                        Ok(()) => info!("✅ پیام به Discord ارسال شد"),
+                        Err(e) => warn!(error = %e, "⚠️ ارسال به Discord ناموفق"),
+                    }
+                }
+            }
+            if let Some(webhook) = &slack_webhook {
+                let payload = format_message_slack(&message);
+                if dry_run {
+                    print_dry_run("slack", &payload.to_string());
+                } else {
+                    match send_slack_message(&tg_client, webhook, &payload)
+                        .instrument(cycle_span.clone())
+                        .await
+                    {
+                        Ok(()) => info!("✅ پیام به Slack ارسال شد"),
+                        Err(e) => warn!(error = %e, "⚠️ ارسال به Slack ناموفق"),
+                    }
+                }
+            }
+
+            if any_sent {
+                mark_success(&last_success);
+                last_post_at = Some(Instant::now());
+                let mut store = store.lock().await;
+                store.state.last_posted = values.clone();
+                store.state.last_post_at = Some(Utc::now());
+                if let Err(e) = store.save() {
+                    warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                }
+                last_posted = values;
+            } else {
+                exit_code = EXIT_SEND_FAILED;
+            }
         }
-        if let Some(v) = rates.get("AED") {
-            text.push_str(&format!("🇦🇪 درهم: {} تومان\n", fmt_int(v / 10)));
+
+        send_admin_notices(
+            &tg_client,
+            &bot_token,
+            admin_chat_id.as_deref(),
+            &admin_notices,
+        )
+        .await;
+
+        // بیرون از چرخه چک می‌شه تا شکست یک چرخه خلاصه رو عقب نندازه؛
+        // تاریخ ارسال در فایل وضعیت می‌مونه که هر روز فقط یک بار فرستاده بشه
+        if let Some(at) = summary_time {
+            let now = Utc::now().with_timezone(&SUMMARY_TZ);
+            let last_sent = store.lock().await.state.last_summary_date;
+            if daily.is_due(now, at, last_sent) {
+                // تاریخچه فقط نرخ‌های تومانی رو نگه می‌داره
+                let mut yearly = HashMap::new();
+                for r in daily.ranges.iter().filter(|r| r.unit == Unit::Toman) {
+                    match db.get_yearly_extremes(&r.code).await {
+                        Ok(Some(extremes)) => {
+                            yearly.insert(r.code.clone(), extremes);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!(currency = %r.code, error = %e, "⚠️ خواندن کف و سقف سالانه ناموفق")
+                        }
+                    }
+                }
+                let text = format_summary(parse_mode, number_style, &daily, &yearly);
+                let mut any_sent = false;
+                for chat_id in &summary_chats {
+                    if dry_run {
+                        print_dry_run(chat_id, &text);
+                        any_sent = true;
+                        continue;
+                    }
+                    let sent = retry_with_backoff(
+                        || {
+                            send_telegram_message(
+                                &tg_client,
+                                &bot_token,
+                                chat_id,
+                                &text,
+                                post_options.notifying(),
+                            )
+                        },
+                        MAX_ATTEMPTS,
+                        RETRY_BASE_DELAY,
+                    )
+                    .await;
+                    match sent {
+                        Ok(_) => {
+                            any_sent = true;
+                            info!(chat_id = %chat_id, "📅 خلاصه‌ی روزانه ارسال شد")
+                        }
+                        Err(e) => {
+                            log_send_failure(chat_id, &e);
+                            if e.is_transient() {
+                                dlq.push(PendingMessage::new(
+                                    chat_id,
+                                    &text,
+                                    post_options.notifying(),
+                                ));
+                            }
+                        }
+                    }
+                }
+                if any_sent {
+                    let mut store = store.lock().await;
+                    store.state.last_summary_date = Some(daily.date);
+                    if let Err(e) = store.save() {
+                        warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+                    }
+                }
+            }
         }
-        if let Some(v) = rates.get("CNY") {
-            text.push_str(&format!("🇨🇳 یوآن چین: {} تومان\n", fmt_int(v / 10)));
+
+        if run_once {
+            break;
         }
 
-        text.push_str(&format!(
-            "\n🇹🇷 لیر ترکیه: {} تومان\n",
-            fmt_int(toman_per_lira_i64)
-        ));
+        // ارسال‌ها await می‌شن، پس اینجا چیزی در صف تلگرام باقی نمونده
+        tokio::select! {
+            _ = ticker.tick() => {}
+            Some(request) = refresh_rx.recv() => refresh = Some(request),
+            _ = &mut shutdown => break,
+        }
+    }
 
-        text.push_str("\n🔄 به‌روزرسانی هر ۱ دقیقه\n\n");
-        text.push_str(&chat_id);
+    info!("🛑 shutting down gracefully");
+    let _ = task_shutdown.send(true);
+    if let Some(server) = http_server
+        && tokio::time::timeout(SHUTDOWN_NOTICE_TIMEOUT, server)
+            .await
+            .is_err()
+    {
+        warn!("⚠️ سرور HTTP به موقع بسته نشد");
+    }
+    if let Some(drainer) = dlq_drainer {
+        let _ = tokio::time::timeout(SHUTDOWN_NOTICE_TIMEOUT, drainer).await;
+    }
+    // بستن صف یعنی ناشر چرخه‌های باقی‌مونده رو بفرسته و DISCONNECT بده
+    drop(mqtt);
+    if let Some(publisher) = mqtt_publisher {
+        let _ = tokio::time::timeout(SHUTDOWN_NOTICE_TIMEOUT, publisher).await;
+    }
+    if !dlq.is_empty() {
+        warn!(count = dlq.len(), "⚠️ پیام‌های صف‌شده ارسال نشدند");
+    }
+    if let Err(e) = store.lock().await.save() {
+        warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+    }
+    db.close().await;
 
-        // send
-        send_telegram_message(&client, &bot_token, &chat_id, &text).await;
+    // با --once هر اجرا «توقف» حساب نمی‌شه
+    if let Some(admin) = admin_chat_id.as_ref().filter(|_| !run_once) {
+        // یک بار و بدون retry، که توقف کانتینر معطل تلگرام نشه
+        let notice = send_telegram_message(
+            &tg_client,
+            &bot_token,
+            admin,
+            "⏹ ربات متوقف شد",
+            SendOptions::default(),
+        );
+        match tokio::time::timeout(SHUTDOWN_NOTICE_TIMEOUT, notice).await {
+            Ok(Ok(_)) => info!(chat_id = %admin, "⏹ پیام توقف به ادمین ارسال شد"),
+            Ok(Err(e)) => warn!(chat_id = %admin, error = %e, "⚠️ ارسال پیام توقف ناموفق"),
+            Err(_) => warn!(chat_id = %admin, "⚠️ ارسال پیام توقف به موقع تموم نشد"),
+        }
+    }
 
-        // wait 60s
-        sleep(Duration::from_secs(60)).await;
+    if run_once {
+        std::process::exit(exit_code);
     }
 }
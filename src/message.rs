@@ -0,0 +1,709 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::RateMap;
+use crate::currency::Currency;
+use crate::jalali::format_jalali;
+
+/// How the message text is marked up for Telegram's `parse_mode`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ParseMode {
+    Plain,
+    MarkdownV2,
+    Html,
+}
+
+impl ParseMode {
+    /// Value for the `parse_mode` API field; `None` means plain text.
+    pub fn api_value(self) -> Option<&'static str> {
+        match self {
+            ParseMode::Plain => None,
+            ParseMode::MarkdownV2 => Some("MarkdownV2"),
+            ParseMode::Html => Some("HTML"),
+        }
+    }
+
+    /// Literal text; escaped so Telegram doesn't read it as markup.
+    pub fn text(self, s: &str) -> String {
+        match self {
+            ParseMode::Plain => s.to_string(),
+            ParseMode::MarkdownV2 => escape_markdown_v2(s),
+            ParseMode::Html => html_escape(s),
+        }
+    }
+
+    pub fn bold(self, s: &str) -> String {
+        match self {
+            ParseMode::Plain => s.to_string(),
+            ParseMode::MarkdownV2 => format!("*{}*", escape_markdown_v2(s)),
+            ParseMode::Html => format!("<b>{}</b>", html_escape(s)),
+        }
+    }
+
+    pub fn italic(self, s: &str) -> String {
+        match self {
+            ParseMode::Plain => s.to_string(),
+            ParseMode::MarkdownV2 => format!("_{}_", escape_markdown_v2(s)),
+            ParseMode::Html => format!("<i>{}</i>", html_escape(s)),
+        }
+    }
+
+    pub fn code(self, s: &str) -> String {
+        match self {
+            ParseMode::Plain => s.to_string(),
+            // داخل `...` فقط ` و \ باید escape بشن
+            ParseMode::MarkdownV2 => format!("`{}`", s.replace('\\', "\\\\").replace('`', "\\`")),
+            ParseMode::Html => format!("<code>{}</code>", html_escape(s)),
+        }
+    }
+}
+
+// https://core.telegram.org/bots/api#markdownv2-style
+const MARKDOWN_V2_RESERVED: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
+];
+
+pub fn escape_markdown_v2(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || MARKDOWN_V2_RESERVED.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// تلگرام فقط همین چهار تا رو در حالت HTML لازم داره
+pub fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn fmt_int(n: i64) -> String {
+    n.to_formatted_string(&Locale::en)
+}
+
+/// Digit style for numbers in the post (`NUMBER_STYLE`).
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum NumberStyle {
+    #[default]
+    Latin,
+    /// ۰۱۲۳ digits, `٬` thousands separator, `٫` decimal point.
+    Persian,
+}
+
+impl NumberStyle {
+    /// Restyles an already formatted number like `-1,234.5` or `+0.9%`.
+    pub fn apply(self, s: &str) -> String {
+        match self {
+            NumberStyle::Latin => s.to_string(),
+            NumberStyle::Persian => to_fa_digits(s)
+                .chars()
+                .map(|c| match c {
+                    ',' => '٬',
+                    '.' => '٫',
+                    '%' => '٪',
+                    _ => c,
+                })
+                .collect(),
+        }
+    }
+
+    /// [`fmt_int`] in this style: `-1234567` → `-۱٬۲۳۴٬۵۶۷`.
+    pub fn int(self, n: i64) -> String {
+        self.apply(&fmt_int(n))
+    }
+}
+
+// درصد تغییر؛ اگه مقدار قبلی صفر باشه معنی نداره
+/// ` (+0.9%)`, or nothing when `previous` is zero.
+pub fn fmt_pct(delta: i64, previous: i64) -> String {
+    if previous == 0 {
+        return String::new();
+    }
+    format!(" ({:+.1}%)", delta as f64 / previous as f64 * 100.0)
+}
+
+/// What a line's `value` is measured in.
+#[derive(Clone, Copy, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub enum Unit {
+    #[default]
+    Toman,
+    /// US dollars, kept in cents so values stay integers (e.g. the gold ounce).
+    UsdCents,
+    /// Toman with this many decimal places, kept as toman × 10^places
+    /// (`RATE_<CODE>_DECIMAL_PLACES`).
+    TomanDecimals(u32),
+}
+
+impl Unit {
+    /// The number without its unit: `1,234`, `2,345.67` or `-0.50`.
+    pub fn amount(self, value: i64) -> String {
+        // علامت جدا نوشته می‌شه، وگرنه -۵۰ سنت با صفرِ قسمت صحیح مثبت می‌شد
+        let decimal = |places: u32| {
+            let scale = 10i64.pow(places);
+            format!(
+                "{}{}.{:0width$}",
+                if value < 0 { "-" } else { "" },
+                fmt_int((value / scale).abs()),
+                (value % scale).abs(),
+                width = places as usize
+            )
+        };
+        match self {
+            Unit::Toman => fmt_int(value),
+            Unit::UsdCents => decimal(2),
+            Unit::TomanDecimals(places) => decimal(places),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Unit::Toman | Unit::TomanDecimals(_) => "تومان",
+            Unit::UsdCents => "دلار",
+        }
+    }
+
+    /// The unit without extra decimals, which history, bounds and alerts use.
+    pub fn base(self) -> Unit {
+        match self {
+            Unit::TomanDecimals(_) => Unit::Toman,
+            unit => unit,
+        }
+    }
+
+    /// `value` converted to [`Unit::base`], rounded to whole toman.
+    pub fn whole(self, value: i64) -> i64 {
+        match self {
+            Unit::TomanDecimals(places) => {
+                (value as f64 / 10f64.powi(places as i32)).round() as i64
+            }
+            _ => value,
+        }
+    }
+}
+
+// 🔺 +500 (+0.9%) / 🔻 -300 (-0.5%) / ➖ نسبت به آخرین پست؛ بار اول چیزی نشون نمی‌ده
+pub fn fmt_delta(current: i64, previous: Option<i64>, unit: Unit) -> String {
+    let Some(previous) = previous else {
+        return String::new();
+    };
+    let delta = current - previous;
+    match delta.signum() {
+        1 => format!("🔺 +{}{}", unit.amount(delta), fmt_pct(delta, previous)),
+        -1 => format!(
+            "🔻 -{}{}",
+            unit.amount(delta.abs()),
+            fmt_pct(delta, previous)
+        ),
+        _ => "➖".to_string(),
+    }
+}
+
+/// How far a value may move and still count as unchanged for skipping a post.
+pub const DEDUP_TOLERANCE_PCT: f64 = 0.05;
+
+/// True when a currency appeared, disappeared, or moved by more than
+/// `tolerance_pct` percent since `previous`.
+pub fn rates_changed(current: &RateMap, previous: &RateMap, tolerance_pct: f64) -> bool {
+    if current.len() != previous.len() {
+        return true;
+    }
+    current
+        .iter()
+        .any(|(code, &value)| match previous.get(code) {
+            None => true,
+            Some(&0) => value != 0,
+            Some(&before) => {
+                ((value - before) as f64 / before as f64 * 100.0).abs() > tolerance_pct
+            }
+        })
+}
+
+/// Values of the last post, keyed by currency code.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PreviousRates(HashMap<Currency, i64>);
+
+impl PreviousRates {
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a RateLine>) -> PreviousRates {
+        PreviousRates(
+            lines
+                .into_iter()
+                .map(|l| (l.currency.clone(), l.value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, currency: &Currency) -> Option<i64> {
+        self.0.get(currency).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// [`rates_changed`] between these values and `previous`.
+    pub fn changed_from(&self, previous: &PreviousRates, tolerance_pct: f64) -> bool {
+        rates_changed(&self.0, &previous.0, tolerance_pct)
+    }
+}
+
+/// One "emoji name: value تومان" row of the post.
+#[derive(Clone)]
+pub struct RateLine {
+    pub currency: Currency,
+    pub emoji: String,
+    pub name: String,
+    pub value: i64,
+    pub previous_value: Option<i64>,
+    pub unit: Unit,
+    /// Set when `value` is the last known good one, with how old it is.
+    pub stale_secs: Option<u64>,
+    /// The fetch failed this cycle and `value` comes from the cache (or
+    /// from a cached USD rate), with how old that value is.
+    pub cached_secs: Option<u64>,
+    /// Extra text after the value, like the sana spread.
+    pub note: Option<String>,
+    /// 📈 / 📉 / ➡️ against the moving average of the last cycles.
+    pub trend: Option<&'static str>,
+}
+
+impl RateLine {
+    /// A line priced in toman.
+    pub fn new(
+        currency: Currency,
+        emoji: &str,
+        name: &str,
+        toman: i64,
+        previous_toman: Option<i64>,
+    ) -> RateLine {
+        RateLine {
+            currency,
+            emoji: emoji.to_string(),
+            name: name.to_string(),
+            value: toman,
+            previous_value: previous_toman,
+            unit: Unit::Toman,
+            stale_secs: None,
+            cached_secs: None,
+            note: None,
+            trend: None,
+        }
+    }
+
+    pub fn with_unit(mut self, unit: Unit) -> RateLine {
+        self.unit = unit;
+        self
+    }
+
+    /// Marks the line as built from a cached value `secs` old, if any.
+    pub fn with_cache_age(mut self, secs: Option<u64>) -> RateLine {
+        self.cached_secs = secs;
+        self
+    }
+
+    /// Fetched and accepted this cycle, not a fallback value.
+    pub fn is_fresh(&self) -> bool {
+        self.stale_secs.is_none() && self.cached_secs.is_none()
+    }
+}
+
+/// A group of lines, optionally under its own heading.
+#[derive(Default)]
+pub struct Section {
+    pub title: Option<String>,
+    pub lines: Vec<RateLine>,
+}
+
+impl Section {
+    pub fn new(lines: Vec<RateLine>) -> Section {
+        Section { title: None, lines }
+    }
+
+    pub fn titled(title: &str, lines: Vec<RateLine>) -> Section {
+        Section {
+            title: Some(title.to_string()),
+            lines,
+        }
+    }
+}
+
+/// Everything that goes into a post, already converted to display units.
+/// Sections are separated by a blank line.
+pub struct Message {
+    pub sections: Vec<Section>,
+    pub footer: String,
+    pub number_style: NumberStyle,
+    /// When the rates were gathered, in Tehran time.
+    pub posted_at: DateTime<Tz>,
+}
+
+impl Message {
+    /// Every line of every section, in display order.
+    pub fn lines(&self) -> impl Iterator<Item = &RateLine> {
+        self.sections.iter().flat_map(|s| s.lines.iter())
+    }
+
+    /// `🗓 1403/06/15 – 14:30 به وقت تهران`, so a forwarded post keeps its time.
+    pub fn date_line(&self) -> String {
+        let text = format!(
+            "🗓 {} – {} به وقت تهران",
+            format_jalali(self.posted_at.date_naive()),
+            self.posted_at.format("%H:%M")
+        );
+        self.number_style.apply(&text)
+    }
+}
+
+const HEADER: &str = "📊 نرخ لحظه‌ای ارز (به تومان):";
+
+/// One rate as it appears in a post, newline included.
+pub fn rate_line(mode: ParseMode, style: NumberStyle, line: &RateLine) -> String {
+    let mut out = format!(
+        "{} {}: {} {}",
+        mode.text(&line.emoji),
+        mode.bold(&line.name),
+        mode.code(&style.apply(&line.unit.amount(line.value))),
+        line.unit.label()
+    );
+    if let Some(trend) = line.trend {
+        out.push(' ');
+        out.push_str(&mode.text(trend));
+    }
+    let delta = fmt_delta(line.value, line.previous_value, line.unit);
+    if !delta.is_empty() {
+        out.push(' ');
+        out.push_str(&mode.text(&style.apply(&delta)));
+    }
+    if let Some(note) = &line.note {
+        out.push(' ');
+        out.push_str(&mode.text(&style.apply(note)));
+    }
+    if let Some(secs) = line.stale_secs {
+        out.push(' ');
+        out.push_str(&mode.text(&format!("🕐 {} پیش", age_label(secs))));
+    }
+    if let Some(secs) = line.cached_secs {
+        out.push(' ');
+        out.push_str(&mode.text(&format!("⏳ {} پیش", age_label(secs))));
+    }
+    out.push('\n');
+    out
+}
+
+pub fn format_message(mode: ParseMode, message: &Message) -> String {
+    match mode {
+        ParseMode::Plain => format_message_plain(message),
+        ParseMode::MarkdownV2 => format_message_markdown(message),
+        ParseMode::Html => format_message_html(message),
+    }
+}
+
+fn render(mode: ParseMode, message: &Message) -> String {
+    let mut text = format!("{}\n", mode.italic(HEADER));
+    text.push_str(&mode.text(&message.date_line()));
+    text.push('\n');
+    for section in message.sections.iter().filter(|s| !s.lines.is_empty()) {
+        text.push('\n');
+        if let Some(title) = &section.title {
+            text.push_str(&mode.bold(title));
+            text.push('\n');
+        }
+        for line in &section.lines {
+            text.push_str(&rate_line(mode, message.number_style, line));
+        }
+    }
+    text.push('\n');
+    text.push_str(&mode.text(&message.footer));
+    text
+}
+
+/// The original plain-text layout.
+pub fn format_message_plain(message: &Message) -> String {
+    render(ParseMode::Plain, message)
+}
+
+/// MarkdownV2 layout: bold names, monospace values, italic header, with
+/// every literal escaped.
+pub fn format_message_markdown(message: &Message) -> String {
+    render(ParseMode::MarkdownV2, message)
+}
+
+/// HTML layout: `<b>` names and `<code>` values; config-provided names and
+/// emoji go through [`html_escape`].
+pub fn format_message_html(message: &Message) -> String {
+    render(ParseMode::Html, message)
+}
+
+pub fn to_fa_digits(s: &str) -> String {
+    s.chars()
+        .map(|c| match c.to_digit(10) {
+            Some(d) => char::from_u32('۰' as u32 + d).unwrap_or(c),
+            None => c,
+        })
+        .collect()
+}
+
+// سن یک نرخ قدیمی: «۵ دقیقه» یا «۲ ساعت»
+pub fn age_label(secs: u64) -> String {
+    if secs < 60 {
+        format!("{} ثانیه", to_fa_digits(&secs.to_string()))
+    } else if secs < 3600 {
+        format!("{} دقیقه", to_fa_digits(&(secs / 60).to_string()))
+    } else {
+        format!("{} ساعت", to_fa_digits(&(secs / 3600).to_string()))
+    }
+}
+
+pub fn interval_label(interval: Duration) -> String {
+    let secs = interval.as_secs();
+    if secs.is_multiple_of(60) {
+        format!("{} دقیقه", to_fa_digits(&(secs / 60).to_string()))
+    } else {
+        format!("{} ثانیه", to_fa_digits(&secs.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn markdown_v2_escapes_every_reserved_char() {
+        for c in "_*[]()~`>#+-=|{}.!".chars() {
+            assert_eq!(escape_markdown_v2(&c.to_string()), format!("\\{}", c));
+        }
+        assert_eq!(escape_markdown_v2("\\"), "\\\\");
+    }
+
+    #[test]
+    fn markdown_v2_escapes_numbers_and_labels() {
+        assert_eq!(escape_markdown_v2("1,025.40"), "1,025\\.40");
+        assert_eq!(escape_markdown_v2("-300"), "\\-300");
+        assert_eq!(escape_markdown_v2("(0.5%)"), "\\(0\\.5%\\)");
+        assert_eq!(escape_markdown_v2("دلار: ۱۰۲٬۵۴۰"), "دلار: ۱۰۲٬۵۴۰");
+    }
+
+    #[test]
+    fn html_escapes_markup_chars() {
+        assert_eq!(html_escape("&"), "&amp;");
+        assert_eq!(html_escape("<"), "&lt;");
+        assert_eq!(html_escape(">"), "&gt;");
+        assert_eq!(html_escape("\""), "&quot;");
+        assert_eq!(
+            html_escape("<b>\"A&B\"</b>"),
+            "&lt;b&gt;&quot;A&amp;B&quot;&lt;/b&gt;"
+        );
+        // بقیه‌ی کاراکترهای MarkdownV2 در HTML دست نمی‌خورن
+        assert_eq!(html_escape("1,025.40 (-0.5%)"), "1,025.40 (-0.5%)");
+    }
+
+    fn usd(toman: i64, previous: Option<i64>) -> RateLine {
+        RateLine::new(Currency::Usd, "💵", "دلار", toman, previous)
+    }
+
+    fn rates(values: &[(Currency, i64)]) -> PreviousRates {
+        let lines: Vec<RateLine> = values
+            .iter()
+            .map(|(c, v)| RateLine::new(c.clone(), "", "", *v, None))
+            .collect();
+        PreviousRates::from_lines(&lines)
+    }
+
+    #[test]
+    fn delta_up_down_and_unchanged() {
+        assert_eq!(
+            fmt_delta(100_500, Some(100_000), Unit::Toman),
+            "🔺 +500 (+0.5%)"
+        );
+        assert_eq!(
+            fmt_delta(99_700, Some(100_000), Unit::Toman),
+            "🔻 -300 (-0.3%)"
+        );
+        assert_eq!(fmt_delta(100_000, Some(100_000), Unit::Toman), "➖");
+        assert_eq!(
+            fmt_delta(231_845, Some(233_000), Unit::UsdCents),
+            "🔻 -11.55 (-0.5%)"
+        );
+    }
+
+    #[test]
+    fn first_post_has_no_delta() {
+        assert_eq!(fmt_delta(100_000, None, Unit::Toman), "");
+        assert_eq!(
+            rate_line(ParseMode::Plain, NumberStyle::Latin, &usd(100_000, None)),
+            "💵 دلار: 100,000 تومان\n"
+        );
+        assert_eq!(
+            rate_line(
+                ParseMode::Plain,
+                NumberStyle::Latin,
+                &usd(100_500, Some(100_000))
+            ),
+            "💵 دلار: 100,500 تومان 🔺 +500 (+0.5%)\n"
+        );
+    }
+
+    #[test]
+    fn delta_from_zero_has_no_percentage() {
+        assert_eq!(fmt_delta(500, Some(0), Unit::Toman), "🔺 +500");
+    }
+
+    #[test]
+    fn changed_from_respects_tolerance() {
+        let before = rates(&[(Currency::Usd, 100_000), (Currency::Eur, 110_000)]);
+        // ۰.۰۵٪ از ۱۰۰٬۰۰۰ یعنی ۵۰ تومان
+        let within = rates(&[(Currency::Usd, 100_050), (Currency::Eur, 110_000)]);
+        let beyond = rates(&[(Currency::Usd, 100_051), (Currency::Eur, 110_000)]);
+        assert!(!within.changed_from(&before, DEDUP_TOLERANCE_PCT));
+        assert!(beyond.changed_from(&before, DEDUP_TOLERANCE_PCT));
+        assert!(!before.changed_from(&before, 0.0));
+    }
+
+    #[test]
+    fn changed_from_when_currencies_differ() {
+        let before = rates(&[(Currency::Usd, 100_000), (Currency::Eur, 110_000)]);
+        let dropped = rates(&[(Currency::Usd, 100_000)]);
+        let swapped = rates(&[(Currency::Usd, 100_000), (Currency::Gbp, 110_000)]);
+        assert!(dropped.changed_from(&before, DEDUP_TOLERANCE_PCT));
+        assert!(swapped.changed_from(&before, DEDUP_TOLERANCE_PCT));
+        // پست اول: چیزی برای مقایسه نیست
+        assert!(before.changed_from(&PreviousRates::default(), DEDUP_TOLERANCE_PCT));
+    }
+
+    #[test]
+    fn persian_style_zero_negative_and_digits() {
+        let fa = NumberStyle::Persian;
+        assert_eq!(fa.int(0), "۰");
+        assert_eq!(fa.int(-1_234_567), "-۱٬۲۳۴٬۵۶۷");
+        assert_eq!(fa.int(102_540), "۱۰۲٬۵۴۰");
+        assert_eq!(fa.apply("1,234.5"), "۱٬۲۳۴٫۵");
+        assert_eq!(fa.apply("🔻 -300 (-0.3%)"), "🔻 -۳۰۰ (-۰٫۳٪)");
+        // رقم فارسی از قبل دست نمی‌خوره
+        assert_eq!(fa.apply("۱۲۳"), "۱۲۳");
+    }
+
+    #[test]
+    fn latin_style_is_unchanged() {
+        let en = NumberStyle::Latin;
+        assert_eq!(en.int(0), "0");
+        assert_eq!(en.int(-1_234_567), "-1,234,567");
+        assert_eq!(en.apply("۱۲۳"), "۱۲۳");
+    }
+
+    #[test]
+    fn amount_keeps_the_sign_of_small_negatives() {
+        assert_eq!(Unit::UsdCents.amount(-50), "-0.50");
+        assert_eq!(Unit::UsdCents.amount(-123_456), "-1,234.56");
+        assert_eq!(Unit::UsdCents.amount(0), "0.00");
+        assert_eq!(Unit::UsdCents.amount(231_845), "2,318.45");
+        assert_eq!(Unit::TomanDecimals(1).amount(-5), "-0.5");
+        assert_eq!(Unit::TomanDecimals(2).amount(4_507), "45.07");
+        assert_eq!(Unit::Toman.amount(-300), "-300");
+        assert_eq!(
+            NumberStyle::Persian.apply(&Unit::UsdCents.amount(-50)),
+            "-۰٫۵۰"
+        );
+    }
+
+    fn message(sections: Vec<Section>) -> Message {
+        Message {
+            sections,
+            footer: "🔄 به‌روزرسانی هر ۱ دقیقه".to_string(),
+            number_style: NumberStyle::Latin,
+            posted_at: chrono_tz::Asia::Tehran
+                .with_ymd_and_hms(2024, 9, 5, 14, 30, 0)
+                .unwrap(),
+        }
+    }
+
+    fn line(currency: Currency, toman: i64) -> RateLine {
+        RateLine::new(
+            currency.clone(),
+            currency.emoji(),
+            currency.name(),
+            toman,
+            None,
+        )
+    }
+
+    #[test]
+    fn partial_cycle_without_usd_and_lira() {
+        // دلار و لیر نیومدن؛ بقیه پست می‌شن
+        let text = format_message_plain(&message(vec![
+            Section::new(vec![
+                line(Currency::Eur, 113_200),
+                line(Currency::Aed, 27_900),
+            ]),
+            Section::titled("🪙 طلا و سکه", vec![]),
+            Section::new(vec![]),
+        ]));
+        assert_eq!(
+            text,
+            "📊 نرخ لحظه‌ای ارز (به تومان):\n\
+             🗓 1403/06/15 – 14:30 به وقت تهران\n\
+             \n\
+             💶 یورو: 113,200 تومان\n\
+             🇦🇪 درهم: 27,900 تومان\n\
+             \n\
+             🔄 به‌روزرسانی هر ۱ دقیقه"
+        );
+    }
+
+    #[test]
+    fn partial_cycle_with_only_derived_lines() {
+        // tgju کلاً جواب نداد ولی لیر از کش دلار ساخته شد
+        let lira = line(Currency::Try, 2_999).with_cache_age(Some(120));
+        let text = format_message_plain(&message(vec![
+            Section::new(vec![]),
+            Section::new(vec![lira]),
+        ]));
+        assert!(text.contains("🇹🇷 لیر ترکیه: 2,999 تومان ⏳ ۲ دقیقه پیش\n"));
+        assert!(!text.contains("دلار"));
+        assert!(!text.contains("\n\n\n"));
+    }
+
+    #[test]
+    fn stale_and_cached_lines_are_marked_in_every_mode() {
+        let mut stale = line(Currency::Usd, 102_540);
+        stale.stale_secs = Some(300);
+        let cached = line(Currency::Eur, 113_200).with_cache_age(Some(45));
+        let msg = message(vec![Section::new(vec![stale, cached])]);
+
+        let plain = format_message_plain(&msg);
+        assert!(plain.contains("💵 دلار: 102,540 تومان 🕐 ۵ دقیقه پیش\n"));
+        assert!(plain.contains("💶 یورو: 113,200 تومان ⏳ ۴۵ ثانیه پیش\n"));
+
+        let html = format_message_html(&msg);
+        assert!(html.contains("<b>دلار</b>: <code>102,540</code> تومان 🕐 ۵ دقیقه پیش"));
+        let markdown = format_message_markdown(&msg);
+        assert!(markdown.contains("*یورو*: `113,200` تومان ⏳ ۴۵ ثانیه پیش"));
+    }
+
+    #[test]
+    fn fmt_int_groups_thousands() {
+        assert_eq!(fmt_int(0), "0");
+        assert_eq!(fmt_int(999), "999");
+        assert_eq!(fmt_int(1000), "1,000");
+        assert_eq!(fmt_int(102_540), "102,540");
+        assert_eq!(fmt_int(1_234_567_890), "1,234,567,890");
+        assert_eq!(fmt_int(-58_000), "-58,000");
+    }
+}
@@ -0,0 +1,147 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use tracing::{info, warn};
+
+/// Prometheus counters and gauges shared between the main loop and `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    fetch_total: IntCounterVec,
+    fetch_duration: HistogramVec,
+    rate_toman: IntGaugeVec,
+    send_total: IntCounterVec,
+}
+
+pub type SharedMetrics = Arc<Metrics>;
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let registry = Registry::new();
+        let fetch_total = IntCounterVec::new(
+            Opts::new(
+                "exchange_fetch_total",
+                "Rate fetches by currency and outcome",
+            ),
+            &["currency", "status"],
+        )
+        .expect("valid exchange_fetch_total metric");
+        // retry و backoff هم جزو زمانه، برای همین سطل‌ها تا ۶۰ ثانیه می‌رن
+        let fetch_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "exchange_fetch_duration_seconds",
+                "Time to fetch a rate, retries included",
+            )
+            .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["currency"],
+        )
+        .expect("valid exchange_fetch_duration_seconds metric");
+        let rate_toman = IntGaugeVec::new(
+            Opts::new("exchange_rate_toman", "Latest rate in toman"),
+            &["currency"],
+        )
+        .expect("valid exchange_rate_toman metric");
+        let send_total = IntCounterVec::new(
+            Opts::new("telegram_send_total", "Telegram posts by outcome"),
+            &["status"],
+        )
+        .expect("valid telegram_send_total metric");
+
+        for collector in [
+            Box::new(fetch_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(fetch_duration.clone()),
+            Box::new(rate_toman.clone()),
+            Box::new(send_total.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric registered once");
+        }
+
+        Metrics {
+            registry,
+            fetch_total,
+            fetch_duration,
+            rate_toman,
+            send_total,
+        }
+    }
+
+    pub fn record_fetch(&self, currency: &str, ok: bool, took: Duration) {
+        self.fetch_total
+            .with_label_values(&[currency, status_label(ok)])
+            .inc();
+        self.fetch_duration
+            .with_label_values(&[currency])
+            .observe(took.as_secs_f64());
+    }
+
+    pub fn set_rate(&self, currency: &str, toman: i64) {
+        self.rate_toman.with_label_values(&[currency]).set(toman);
+    }
+
+    pub fn record_send(&self, ok: bool) {
+        self.send_total.with_label_values(&[status_label(ok)]).inc();
+    }
+
+    fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buf = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+fn status_label(ok: bool) -> &'static str {
+    if ok { "ok" } else { "error" }
+}
+
+/// `GET /metrics` in the Prometheus text format.
+pub async fn metrics_handler(State(metrics): State<SharedMetrics>) -> impl IntoResponse {
+    match metrics.render() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+            body,
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            [(header::CONTENT_TYPE, "text/plain")],
+            e.to_string(),
+        ),
+    }
+}
+
+/// Serves `/metrics` on `port`. A bind failure is logged and the task ends
+/// without touching the bot itself.
+pub async fn run_metrics_server(port: u16, metrics: SharedMetrics) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(%addr, error = %e, "⚠️ سرور metrics بالا نیومد");
+            return;
+        }
+    };
+    info!(%addr, "📈 metrics endpoint listening on /metrics");
+
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+    if let Err(e) = axum::serve(listener, app).await {
+        warn!(error = %e, "⚠️ سرور metrics متوقف شد");
+    }
+}
@@ -0,0 +1,286 @@
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, MissedTickBehavior, interval, sleep, timeout};
+use tracing::{debug, info, warn};
+
+use crate::storage::unix_now;
+
+/// Cycles waiting for the publisher before new ones are dropped.
+pub const MQTT_CHANNEL_SIZE: usize = 16;
+
+const DEFAULT_MQTT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+// برای CONNACK، PUBACK و PINGRESP
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(60);
+
+/// `(code, toman)` for every currency of one cycle.
+pub type RateBatch = Vec<(String, i64)>;
+
+/// Where `MQTT_BROKER_URL` points: `mqtt://[user[:password]@]host[:port]`.
+#[derive(Debug, Clone)]
+pub struct MqttBroker {
+    host: String,
+    port: u16,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl MqttBroker {
+    pub fn parse(url: &str) -> Result<MqttBroker, String> {
+        let rest = url
+            .trim()
+            .strip_prefix("mqtt://")
+            .ok_or_else(|| format!("'{}' should start with mqtt://", url))?
+            .trim_end_matches('/');
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, rest),
+        };
+        let (username, password) = match credentials.map(|c| c.split_once(':')) {
+            Some(Some((user, pass))) => (Some(user.to_string()), Some(pass.to_string())),
+            Some(None) => (credentials.map(str::to_string), None),
+            None => (None, None),
+        };
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>()
+                    .map_err(|e| format!("invalid port in '{}': {}", url, e))?,
+            ),
+            None => (address, DEFAULT_MQTT_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("no host in '{}'", url));
+        }
+        Ok(MqttBroker {
+            host: host.to_string(),
+            port,
+            username,
+            password,
+        })
+    }
+}
+
+// طول باقی‌مونده‌ی بسته، ۷ بیت در هر بایت
+fn push_remaining_len(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn push_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn packet(first: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![first];
+    push_remaining_len(&mut out, body.len());
+    out.extend_from_slice(body);
+    out
+}
+
+fn connect_packet(broker: &MqttBroker, client_id: &str) -> Vec<u8> {
+    // clean session؛ چیزی subscribe نمی‌کنیم که نگه داشتنش معنی داشته باشه
+    let mut flags = 0x02;
+    if broker.username.is_some() {
+        flags |= 0x80;
+    }
+    if broker.password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    push_str(&mut body, "MQTT");
+    body.push(4);
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    push_str(&mut body, client_id);
+    for field in [&broker.username, &broker.password].into_iter().flatten() {
+        push_str(&mut body, field);
+    }
+    packet(0x10, &body)
+}
+
+// QoS 1؛ بار دوم با پرچم DUP
+fn publish_packet(topic: &str, payload: &str, packet_id: u16, dup: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_str(&mut body, topic);
+    body.extend_from_slice(&packet_id.to_be_bytes());
+    body.extend_from_slice(payload.as_bytes());
+    packet(if dup { 0x3A } else { 0x32 }, &body)
+}
+
+async fn read_packet(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let kind = stream.read_u8().await?;
+    let mut len = 0usize;
+    for shift in 0..4 {
+        let byte = stream.read_u8().await?;
+        len |= usize::from(byte & 0x7F) << (7 * shift);
+        if byte & 0x80 == 0 {
+            let mut body = vec![0u8; len];
+            stream.read_exact(&mut body).await?;
+            return Ok((kind >> 4, body));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed remaining length",
+    ))
+}
+
+// بسته‌ها رو می‌خونه تا یکی از نوع `kind` برسه
+async fn expect(stream: &mut TcpStream, kind: u8) -> io::Result<Vec<u8>> {
+    let wait = async {
+        loop {
+            let (got, body) = read_packet(stream).await?;
+            if got == kind {
+                return Ok(body);
+            }
+            debug!(packet = got, "MQTT packet ignored");
+        }
+    };
+    timeout(REPLY_TIMEOUT, wait)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no reply from broker"))?
+}
+
+async fn connect(broker: &MqttBroker, client_id: &str) -> io::Result<TcpStream> {
+    let mut stream = timeout(
+        REPLY_TIMEOUT,
+        TcpStream::connect((broker.host.as_str(), broker.port)),
+    )
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??;
+    stream.write_all(&connect_packet(broker, client_id)).await?;
+    let ack = expect(&mut stream, 2).await?;
+    match ack.get(1) {
+        Some(0) => Ok(stream),
+        code => Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("broker refused connection (code {:?})", code),
+        )),
+    }
+}
+
+struct Session {
+    stream: TcpStream,
+    next_id: u16,
+}
+
+impl Session {
+    fn packet_id(&mut self) -> u16 {
+        // صفر شناسه‌ی معتبری نیست
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        self.next_id
+    }
+
+    // همه رو می‌فرسته و بعد منتظر PUBACK همه‌شون می‌مونه
+    async fn publish(&mut self, batch: &RateBatch, dup: bool) -> io::Result<()> {
+        let mut pending = Vec::with_capacity(batch.len());
+        for (code, toman) in batch {
+            let id = self.packet_id();
+            let topic = format!("rates/{}/toman", code);
+            self.stream
+                .write_all(&publish_packet(&topic, &toman.to_string(), id, dup))
+                .await?;
+            pending.push(id);
+        }
+        while !pending.is_empty() {
+            let ack = expect(&mut self.stream, 4).await?;
+            if let [hi, lo, ..] = ack[..] {
+                let id = u16::from_be_bytes([hi, lo]);
+                pending.retain(|p| *p != id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn ping(&mut self) -> io::Result<()> {
+        self.stream.write_all(&[0xC0, 0x00]).await?;
+        expect(&mut self.stream, 13).await.map(|_| ())
+    }
+}
+
+/// Publishes every batch from `rx` as `rates/{code}/toman` with the toman
+/// value as payload, QoS 1. A lost connection is retried with backoff; the
+/// last batch that wasn't acknowledged is sent again once it's back, and
+/// older ones are skipped. Ends when `rx` is closed.
+pub async fn run_mqtt_publisher(broker: MqttBroker, mut rx: mpsc::Receiver<RateBatch>) {
+    let client_id = format!("peybot_rust-{:x}", unix_now());
+    let mut unsent: Option<RateBatch> = None;
+    let mut backoff = RECONNECT_MIN;
+
+    loop {
+        let mut session = match connect(&broker, &client_id).await {
+            Ok(stream) => {
+                info!(host = %broker.host, port = broker.port, "📡 MQTT connected");
+                backoff = RECONNECT_MIN;
+                Session { stream, next_id: 0 }
+            }
+            Err(e) => {
+                warn!(host = %broker.host, error = %e, retry_in_secs = backoff.as_secs(), "⚠️ اتصال به MQTT ناموفق");
+                // در این فاصله فقط آخرین چرخه نگه داشته می‌شه
+                let until = Instant::now() + backoff;
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(until) => break,
+                        batch = rx.recv() => match batch {
+                            Some(batch) => unsent = Some(batch),
+                            None => return,
+                        },
+                    }
+                }
+                backoff = (backoff * 2).min(RECONNECT_MAX);
+                continue;
+            }
+        };
+
+        if let Some(batch) = unsent.take()
+            && let Err(e) = session.publish(&batch, true).await
+        {
+            warn!(error = %e, "⚠️ اتصال MQTT قطع شد");
+            unsent = Some(batch);
+            sleep(RECONNECT_MIN).await;
+            continue;
+        }
+
+        let mut keep_alive = interval(KEEP_ALIVE / 2);
+        keep_alive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        keep_alive.tick().await;
+        let lost = loop {
+            tokio::select! {
+                batch = rx.recv() => {
+                    let Some(batch) = batch else {
+                        let _ = session.stream.write_all(&[0xE0, 0x00]).await;
+                        return;
+                    };
+                    if let Err(e) = session.publish(&batch, false).await {
+                        unsent = Some(batch);
+                        break e;
+                    }
+                    debug!("MQTT rates published");
+                }
+                _ = keep_alive.tick() => {
+                    if let Err(e) = session.ping().await {
+                        break e;
+                    }
+                }
+            }
+        };
+        warn!(error = %lost, "⚠️ اتصال MQTT قطع شد");
+    }
+}
@@ -0,0 +1,99 @@
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::time::sleep;
+use tracing::warn;
+
+use crate::error::BotError;
+
+pub const MAX_ATTEMPTS: u32 = 3;
+pub const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+// retry_after بیشتر از این (بازه‌ی پیش‌فرض به‌روزرسانی) یعنی چرخه‌ی بعد زودتر می‌رسه؛
+// صبر نمی‌کنیم و همین‌جا تموم می‌شه
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+// تا نصف تأخیر رو تصادفی اضافه می‌کنیم که چند درخواست با هم دوباره نخورن
+fn with_jitter(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let max_extra = delay.as_millis() as u64 / 2;
+    if max_extra == 0 {
+        return delay;
+    }
+    delay + Duration::from_millis(nanos as u64 % max_extra)
+}
+
+/// Runs `f` up to `max_attempts` times, doubling the delay after each failure.
+/// Only the last error is returned, wrapped with the number of attempts made.
+/// Errors that aren't transient (see [`BotError::is_transient`]) stop early,
+/// and a Telegram `retry_after` replaces the backoff delay, unless it is
+/// longer than [`MAX_RETRY_AFTER`], which gives up at once.
+pub async fn retry_with_backoff<F, Fut, T>(
+    mut f: F,
+    max_attempts: u32,
+    base_delay: Duration,
+) -> Result<T, BotError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, BotError>>,
+{
+    let mut delay = base_delay;
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(v) => return Ok(v),
+            Err(e)
+                if attempt >= max_attempts
+                    || !e.is_transient()
+                    || e.retry_after().is_some_and(|wait| wait > MAX_RETRY_AFTER) =>
+            {
+                return Err(BotError::RetriesExhausted {
+                    attempts: attempt,
+                    source: Box::new(e),
+                });
+            }
+            Err(e) => {
+                warn!(attempt, max_attempts, error = %e, "🔁 تلاش ناموفق بود");
+                // اگه تلگرام خودش گفته چقدر صبر کنیم، همون رو رعایت می‌کنیم
+                match e.retry_after() {
+                    Some(wait) => sleep(wait).await,
+                    None => {
+                        sleep(with_jitter(delay)).await;
+                        delay *= 2;
+                    }
+                }
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn long_retry_after_gives_up_without_waiting() {
+        let mut calls = 0;
+        let result: Result<(), BotError> = tokio::time::timeout(
+            Duration::from_secs(5),
+            retry_with_backoff(
+                || {
+                    calls += 1;
+                    async { Err(BotError::TelegramRateLimited { retry_after: 3_600 }) }
+                },
+                MAX_ATTEMPTS,
+                RETRY_BASE_DELAY,
+            ),
+        )
+        .await
+        .expect("a one-hour retry_after must not be slept");
+        assert!(matches!(
+            result,
+            Err(BotError::RetriesExhausted { attempts: 1, .. })
+        ));
+        assert_eq!(calls, 1);
+    }
+}
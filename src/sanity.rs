@@ -0,0 +1,430 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use tracing::{error, info, warn};
+
+use crate::config::CurrencyConfig;
+use crate::error::BotError;
+use crate::message::{RateLine, Section};
+use crate::storage::unix_now;
+
+/// Largest single-cycle move accepted by default, in percent.
+pub const DEFAULT_MAX_JUMP_PCT: f64 = 20.0;
+
+// یک جهش واقعی (مثلاً بعد از یک خبر مهم) بعد از این تعداد چرخه‌ی پشت سر هم پذیرفته می‌شه
+const JUMP_CONFIRM_CYCLES: u32 = 3;
+
+/// Inclusive range a value must fall in, in the line's own unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bounds {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl Bounds {
+    pub fn new(min: i64, max: i64) -> Bounds {
+        Bounds {
+            min: Some(min),
+            max: Some(max),
+        }
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+// بازه‌های پیش‌فرض خیلی بازن؛ فقط برای گرفتن عددهای کاملاً بی‌معنی (صفر، ریال به جای تومان، ...)
+fn default_bounds(code: &str) -> Bounds {
+    match code {
+        "USD" | "USDT" | "SANA_SELL" | "SANA_BUY" | "EUR" | "GBP" | "CHF" => {
+            Bounds::new(10_000, 2_000_000)
+        }
+        "CAD" | "AUD" => Bounds::new(5_000, 1_500_000),
+        // دینار کویت از دلار گرون‌تره
+        "KWD" => Bounds::new(30_000, 6_000_000),
+        // هر دینار عراق فقط چند ده تومانه
+        "IQD" => Bounds::new(5, 2_000),
+        "AED" | "SAR" => Bounds::new(2_000, 600_000),
+        // روبل فقط چند صد تا چند هزار تومانه
+        "RUB" => Bounds::new(50, 30_000),
+        "CNY" => Bounds::new(1_000, 300_000),
+        "TRY" => Bounds::new(300, 60_000),
+        "GOLD18" | "GOLD24" => Bounds::new(500_000, 200_000_000),
+        "SEKEE" | "NIM_SEKEE" => Bounds::new(5_000_000, 3_000_000_000),
+        // به سنت
+        "ONS" => Bounds::new(50_000, 2_000_000),
+        // قیمت دلاری ضربدر نرخ دلار، به تومان
+        "SILVER" => Bounds::new(200_000, 100_000_000),
+        "BRENT" => Bounds::new(500_000, 200_000_000),
+        _ => Bounds::default(),
+    }
+}
+
+/// Bounds per currency code: the defaults above, overridden by `min`/`max`
+/// from the currency config, overridden in turn by `RATE_MIN_*`/`RATE_MAX_*`.
+pub fn build_bounds(
+    currencies: &[CurrencyConfig],
+    env_overrides: &HashMap<String, Bounds>,
+) -> HashMap<String, Bounds> {
+    let mut bounds: HashMap<String, Bounds> = ["USDT", "TRY"]
+        .into_iter()
+        .map(|code| (code.to_string(), default_bounds(code)))
+        .collect();
+    for c in currencies {
+        let default = default_bounds(c.code.code());
+        bounds.insert(
+            c.code.to_string(),
+            Bounds {
+                min: c.min.or(default.min),
+                max: c.max.or(default.max),
+            },
+        );
+    }
+    for (code, o) in env_overrides {
+        let b = bounds.entry(code.clone()).or_default();
+        b.min = o.min.or(b.min);
+        b.max = o.max.or(b.max);
+    }
+    bounds
+}
+
+/// Returns `value` if it is inside the bounds configured for `currency`;
+/// a currency without bounds always passes.
+pub fn validate_rate(
+    currency: &str,
+    value: i64,
+    bounds: &HashMap<String, Bounds>,
+) -> Result<i64, BotError> {
+    let b = bounds.get(currency).copied().unwrap_or_default();
+    if b.contains(value) {
+        return Ok(value);
+    }
+    Err(BotError::RateOutOfBounds {
+        currency: currency.to_string(),
+        value,
+        min: b.min.unwrap_or(i64::MIN),
+        max: b.max.unwrap_or(i64::MAX),
+    })
+}
+
+/// Why a fetched value wasn't trusted.
+#[derive(Debug)]
+pub enum Rejection {
+    OutOfBounds(BotError),
+    Jump {
+        value: i64,
+        last_good: i64,
+        pct: f64,
+    },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::OutOfBounds(e) => write!(f, "{}", e),
+            Rejection::Jump {
+                value,
+                last_good,
+                pct,
+            } => write!(f, "{} is {:+.1}% from last good {}", value, pct, last_good),
+        }
+    }
+}
+
+/// Percent change from `from` to `to`; `None` when `from` is zero.
+pub fn pct_change(from: i64, to: i64) -> Option<f64> {
+    (from != 0).then(|| (to - from) as f64 / from as f64 * 100.0)
+}
+
+/// Checks `value` against the last good value. A move of exactly
+/// `max_jump_pct` is still accepted.
+pub fn check_jump(value: i64, last_good: Option<i64>, max_jump_pct: f64) -> Result<(), Rejection> {
+    if let Some(last_good) = last_good
+        && let Some(pct) = pct_change(last_good, value)
+        && pct.abs() > max_jump_pct
+    {
+        return Err(Rejection::Jump {
+            value,
+            last_good,
+            pct,
+        });
+    }
+    Ok(())
+}
+
+/// Keeps the last accepted line per currency and swaps rejected values for it.
+pub struct SanityGuard {
+    bounds: HashMap<String, Bounds>,
+    max_jump_pct: f64,
+    last_good: HashMap<String, (RateLine, i64)>,
+    // code -> (مقدار رد شده، چند چرخه پشت سر هم)
+    pending_jumps: HashMap<String, (i64, u32)>,
+    rejected_last_cycle: HashSet<String>,
+}
+
+impl SanityGuard {
+    pub fn new(bounds: HashMap<String, Bounds>, max_jump_pct: f64) -> SanityGuard {
+        SanityGuard {
+            bounds,
+            max_jump_pct,
+            last_good: HashMap::new(),
+            pending_jumps: HashMap::new(),
+            rejected_last_cycle: HashSet::new(),
+        }
+    }
+
+    /// Validates every line in place. Rejected lines are replaced by the last
+    /// good value marked stale, or dropped if there is none. Returns the
+    /// rejections that are new since the previous cycle, for admin reports.
+    pub fn apply(&mut self, sections: &mut [Section]) -> Vec<(String, Rejection)> {
+        let now = unix_now();
+        let mut rejected_now = HashSet::new();
+        let mut fresh = Vec::new();
+
+        for section in sections.iter_mut() {
+            let lines = std::mem::take(&mut section.lines);
+            for line in lines {
+                match self.check(&line) {
+                    Ok(()) => {
+                        self.pending_jumps.remove(line.currency.code());
+                        self.last_good
+                            .insert(line.currency.to_string(), (line.clone(), now));
+                        section.lines.push(line);
+                    }
+                    Err(rejection) => {
+                        error!(currency = %line.currency, reason = %rejection, "🚨 rejected implausible rate");
+                        if !self.rejected_last_cycle.contains(line.currency.code()) {
+                            fresh.push((line.currency.to_string(), rejection));
+                        }
+                        rejected_now.insert(line.currency.to_string());
+                        if let Some((good, at)) = self.last_good.get(line.currency.code()) {
+                            let mut stale = good.clone();
+                            stale.previous_value = line.previous_value;
+                            stale.stale_secs = Some((now - at).max(0) as u64);
+                            section.lines.push(stale);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.rejected_last_cycle = rejected_now;
+        fresh
+    }
+
+    fn check(&mut self, line: &RateLine) -> Result<(), Rejection> {
+        // بازه‌ها به تومان کامل‌ان، حتی برای ارزی که با اعشار نشون داده می‌شه
+        validate_rate(
+            line.currency.code(),
+            line.unit.whole(line.value),
+            &self.bounds,
+        )
+        .map_err(Rejection::OutOfBounds)?;
+        let last_good = self
+            .last_good
+            .get(line.currency.code())
+            .map(|(l, _)| l.value);
+        match check_jump(line.value, last_good, self.max_jump_pct) {
+            Err(Rejection::Jump { .. }) if self.confirm_jump(line) => {
+                info!(currency = %line.currency, value = line.value, "✅ جهش قیمت چند چرخه تکرار شد و پذیرفته شد");
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    // اگه همون مقدار جدید (با ۱٪ تلورانس) چند چرخه پشت سر هم بیاد، واقعیه
+    fn confirm_jump(&mut self, line: &RateLine) -> bool {
+        let entry = self
+            .pending_jumps
+            .entry(line.currency.to_string())
+            .or_insert((line.value, 0));
+        let same = pct_change(entry.0, line.value).is_some_and(|pct| pct.abs() <= 1.0);
+        *entry = if same {
+            (line.value, entry.1 + 1)
+        } else {
+            (line.value, 1)
+        };
+        entry.1 >= JUMP_CONFIRM_CYCLES
+    }
+}
+
+/// Spots values that stop moving, e.g. when tgju keeps serving a cached page.
+pub struct StalenessTracker {
+    stale_cycles: u32,
+    alert_cycles: u32,
+    // code -> (مقدار، چند چرخه بدون تغییر، از کِی)
+    unchanged: HashMap<String, (i64, u32, i64)>,
+}
+
+impl StalenessTracker {
+    pub fn new(stale_cycles: u32, alert_cycles: u32) -> StalenessTracker {
+        StalenessTracker {
+            stale_cycles,
+            alert_cycles,
+            unchanged: HashMap::new(),
+        }
+    }
+
+    /// Marks lines unchanged for more than `stale_cycles` cycles with their
+    /// age. Returns the codes that just reached `alert_cycles`, once each.
+    pub fn apply(&mut self, sections: &mut [Section]) -> Vec<String> {
+        let now = unix_now();
+        let mut alerts = Vec::new();
+        let lines = sections
+            .iter_mut()
+            .flat_map(|s| s.lines.iter_mut())
+            // مقدارهای جایگزین (sanity یا کش) خودشون قدیمی‌ان
+            .filter(|l| l.is_fresh());
+        for line in lines {
+            let entry = self
+                .unchanged
+                .entry(line.currency.to_string())
+                .or_insert((line.value, 0, now));
+            if entry.0 == line.value {
+                entry.1 += 1;
+            } else {
+                *entry = (line.value, 0, now);
+            }
+
+            let (_, count, since) = *entry;
+            if count > self.stale_cycles {
+                warn!(currency = %line.currency, cycles = count, "🕐 نرخ چند چرخه است تغییر نکرده");
+                line.stale_secs = Some((now - since).max(0) as u64);
+            }
+            if count == self.alert_cycles {
+                alerts.push(line.currency.to_string());
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::builtin_currencies;
+    use crate::currency::Currency;
+
+    fn usd_bounds() -> HashMap<String, Bounds> {
+        HashMap::from([("USD".to_string(), Bounds::new(10_000, 2_000_000))])
+    }
+
+    fn usd_sections(toman: i64) -> Vec<Section> {
+        vec![Section::new(vec![RateLine::new(
+            Currency::Usd,
+            "💵",
+            "دلار",
+            toman,
+            None,
+        )])]
+    }
+
+    #[test]
+    fn bounds_are_inclusive() {
+        let b = Bounds::new(10_000, 2_000_000);
+        assert!(!b.contains(9_999));
+        assert!(b.contains(10_000));
+        assert!(b.contains(2_000_000));
+        assert!(!b.contains(2_000_001));
+        assert!(Bounds::default().contains(i64::MIN));
+    }
+
+    #[test]
+    fn validate_rate_edges() {
+        let bounds = usd_bounds();
+        assert_eq!(validate_rate("USD", 10_000, &bounds).unwrap(), 10_000);
+        assert!(matches!(
+            validate_rate("USD", 9_999, &bounds),
+            Err(BotError::RateOutOfBounds { min: 10_000, .. })
+        ));
+        assert!(validate_rate("USD", 2_000_001, &bounds).is_err());
+        // ارز بدون بازه همیشه قبوله
+        assert_eq!(validate_rate("XYZ", -1, &bounds).unwrap(), -1);
+    }
+
+    #[test]
+    fn jump_of_exactly_the_threshold_is_accepted() {
+        let max = DEFAULT_MAX_JUMP_PCT;
+        assert!(check_jump(120_000, Some(100_000), max).is_ok());
+        assert!(check_jump(80_000, Some(100_000), max).is_ok());
+        assert!(check_jump(120_001, Some(100_000), max).is_err());
+        assert!(check_jump(79_999, Some(100_000), max).is_err());
+        assert!(check_jump(1_000_000, None, max).is_ok());
+        // بدون مبنا (صفر) درصد تغییر معنی نداره
+        assert!(check_jump(1_000_000, Some(0), max).is_ok());
+    }
+
+    #[test]
+    fn pct_change_sign() {
+        assert_eq!(pct_change(100_000, 120_000), Some(20.0));
+        assert_eq!(pct_change(100_000, 80_000), Some(-20.0));
+        assert_eq!(pct_change(0, 80_000), None);
+    }
+
+    #[test]
+    fn env_overrides_config_overrides_defaults() {
+        let mut currencies = builtin_currencies();
+        let usd = currencies.iter_mut().find(|c| c.code == "USD").unwrap();
+        usd.min = Some(40_000);
+        let overrides = HashMap::from([(
+            "USD".to_string(),
+            Bounds {
+                min: None,
+                max: Some(150_000),
+            },
+        )]);
+        let bounds = build_bounds(&currencies, &overrides);
+        assert_eq!(bounds["USD"], Bounds::new(40_000, 150_000));
+        assert_eq!(bounds["EUR"], Bounds::new(10_000, 2_000_000));
+        // USDT و TRY در فهرست ارزها نیستن ولی بازه دارن
+        assert_eq!(bounds["TRY"], Bounds::new(300, 60_000));
+    }
+
+    #[test]
+    fn out_of_bounds_value_is_dropped_without_a_last_good_one() {
+        let mut guard = SanityGuard::new(usd_bounds(), DEFAULT_MAX_JUMP_PCT);
+        let mut sections = usd_sections(9_999);
+        let rejected = guard.apply(&mut sections);
+        assert_eq!(rejected.len(), 1);
+        assert!(sections[0].lines.is_empty());
+    }
+
+    #[test]
+    fn jump_is_replaced_by_the_last_good_value() {
+        let mut guard = SanityGuard::new(usd_bounds(), DEFAULT_MAX_JUMP_PCT);
+        guard.apply(&mut usd_sections(100_000));
+
+        let mut sections = usd_sections(120_001);
+        let rejected = guard.apply(&mut sections);
+        assert!(matches!(rejected[..], [(_, Rejection::Jump { .. })]));
+        let line = &sections[0].lines[0];
+        assert_eq!(line.value, 100_000);
+        assert!(line.stale_secs.is_some());
+
+        // همون رد شدن دوباره به ادمین گزارش نمی‌شه
+        let mut sections = usd_sections(150_000);
+        assert!(guard.apply(&mut sections).is_empty());
+        assert_eq!(sections[0].lines[0].value, 100_000);
+
+        let mut sections = usd_sections(120_000);
+        assert!(guard.apply(&mut sections).is_empty());
+        assert!(sections[0].lines[0].is_fresh());
+    }
+
+    #[test]
+    fn repeated_jump_is_accepted_after_confirmation() {
+        let mut guard = SanityGuard::new(usd_bounds(), DEFAULT_MAX_JUMP_PCT);
+        guard.apply(&mut usd_sections(100_000));
+        for _ in 1..JUMP_CONFIRM_CYCLES {
+            let mut sections = usd_sections(130_000);
+            guard.apply(&mut sections);
+            assert_eq!(sections[0].lines[0].value, 100_000);
+        }
+        let mut sections = usd_sections(130_500);
+        guard.apply(&mut sections);
+        assert_eq!(sections[0].lines[0].value, 130_500);
+        assert!(sections[0].lines[0].is_fresh());
+    }
+}
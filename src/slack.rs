@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, StatusCode};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use crate::error::BotError;
+use crate::message::{Message, ParseMode, format_message_plain, rate_line};
+
+// وقتی Slack سرآیند Retry-After نفرستاد
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+// بیشتر از این صبر نمی‌کنیم که چرخه معطل نمونه
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+// Slack فقط این سه کاراکتر رو escape می‌خواد
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The webhook payload for one post: a single `section` block with the rate
+/// table in mrkdwn (section titles in bold, one line per rate), plus the
+/// plain-text post as `text` for clients and notifications that don't
+/// render blocks.
+///
+/// It takes the whole [`Message`] rather than just the values so the lines
+/// keep their names, units and order.
+pub fn format_message_slack(message: &Message) -> Value {
+    let mut table = format!("*{}*\n", escape(&message.date_line()));
+    for section in message.sections.iter().filter(|s| !s.lines.is_empty()) {
+        table.push('\n');
+        if let Some(title) = &section.title {
+            table.push_str(&format!("*{}*\n", escape(title)));
+        }
+        for line in &section.lines {
+            table.push_str(&escape(&rate_line(
+                ParseMode::Plain,
+                message.number_style,
+                line,
+            )));
+        }
+    }
+    json!({
+        "text": escape(&format_message_plain(message)),
+        "blocks": [
+            {
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": table.trim_end() },
+            }
+        ],
+    })
+}
+
+/// Posts `payload` to a Slack incoming webhook. A 429 is retried once after
+/// the `Retry-After` delay.
+pub async fn send_slack_message(
+    client: &Client,
+    webhook_url: &str,
+    payload: &Value,
+) -> Result<(), BotError> {
+    let mut retried = false;
+    loop {
+        let resp = client
+            .post(webhook_url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|source| BotError::WebhookSend {
+                service: "Slack",
+                source,
+            })?;
+
+        let status = resp.status();
+        if status == StatusCode::TOO_MANY_REQUESTS && !retried {
+            let wait = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs)
+                .min(MAX_RETRY_AFTER);
+            warn!(retry_after_secs = wait.as_secs(), "⏳ Slack rate limit");
+            tokio::time::sleep(wait).await;
+            retried = true;
+            continue;
+        }
+        let body = resp.text().await.unwrap_or_default();
+        if status.is_success() {
+            debug!(status = status.as_u16(), "📨 slack response");
+            return Ok(());
+        }
+        return Err(BotError::WebhookApi {
+            service: "Slack",
+            status,
+            description: body,
+        });
+    }
+}
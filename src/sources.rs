@@ -0,0 +1,1127 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::Client;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::config::{CurrencyConfig, PriceUnit, tgju_lira_currency};
+use crate::currency::{Currency, Rial};
+use crate::error::{BotError, FetchError};
+use crate::message::fmt_int;
+use crate::retry::{MAX_ATTEMPTS, RETRY_BASE_DELAY, retry_with_backoff};
+use crate::storage::unix_now;
+
+// به ترتیب امتحان می‌شن؛ اولی قالب فعلی صفحه‌ی پروفایل tgju است
+const TGJU_PRICE_SELECTORS: &[&str] = &[
+    ".top-mobile-block .block-last-change-percentage .price",
+    "[data-col='info.last_trade.PDrCotVal']",
+    ".block-last-change-percentage .price",
+];
+
+// وقتی هیچ سلکتوری جواب نده (قالب صفحه عوض شده)، عدد از خود متن HTML پیدا می‌شه:
+// span با data-col قیمت حتی اگه HTML خراب باشه، JSON جاسازی‌شده، و متن «نرخ فعلی»
+static TGJU_DATA_COL_PRICE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"data-col=["']info\.last_trade\.PDrCotVal["'][^>]*>\s*([0-9۰-۹][0-9۰-۹,٬.]*)"#)
+        .expect("valid regex")
+});
+static TGJU_LABEL_PRICE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"نرخ فعلی[^0-9۰-۹]{0,80}([0-9۰-۹][0-9۰-۹,٬.]*)").expect("valid regex")
+});
+// JSON صفحه ابزارهای دیگه (تیکر، نوار کناری) رو هم داره؛ "p" فقط از شیء ابزاری
+// خونده می‌شه که "item"ش با آخر آدرس صفحه یکیه
+static TGJU_JSON_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""item"\s*:\s*"([^"]*)""#).expect("valid regex"));
+static TGJU_JSON_LAST_TRADE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#""last_trade"\s*:\s*\{[^{}]*?"p"\s*:\s*"([0-9][0-9,.]*)""#).expect("valid regex")
+});
+
+// هشدار عوض شدن قالب برای هر صفحه فقط یک بار، تا سلکتور اصلی دوباره جواب بده
+static PRIMARY_MISSED: LazyLock<Mutex<HashSet<String>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Which path found the price on a tgju page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Extraction {
+    /// The first selector: the currency's own `selector`, or the current layout.
+    Primary,
+    /// A later selector in the list, by position.
+    Fallback(usize),
+    /// A regex over the raw HTML, after every selector missed.
+    Regex,
+}
+
+impl fmt::Display for Extraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Extraction::Primary => write!(f, "primary selector"),
+            Extraction::Fallback(i) => write!(f, "fallback selector #{}", i),
+            Extraction::Regex => write!(f, "regex"),
+        }
+    }
+}
+
+// یک بار parse می‌شن؛ غلط بودنشون باگ کده و همون اول برنامه panic می‌کنه
+static DEFAULT_TGJU_SELECTORS: LazyLock<Vec<Selector>> = LazyLock::new(|| {
+    TGJU_PRICE_SELECTORS
+        .iter()
+        .map(|s| parse_selector(s).unwrap_or_else(|e| panic!("{}", e)))
+        .collect()
+});
+
+fn parse_selector(selector: &str) -> Result<Selector, FetchError> {
+    Selector::parse(selector).map_err(|e| FetchError::InvalidSelector {
+        selector: selector.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Selectors to try for a currency: its `selector` from config first, then
+/// the built-in candidates. An invalid override is reported here, at startup.
+pub fn tgju_selectors(c: &CurrencyConfig) -> Result<Vec<Selector>, FetchError> {
+    let mut selectors = Vec::new();
+    if let Some(custom) = &c.selector {
+        selectors.push(parse_selector(custom)?);
+    }
+    selectors.extend(DEFAULT_TGJU_SELECTORS.iter().cloned());
+    Ok(selectors)
+}
+
+const BTCTURK_TICKER_URL: &str = "https://api.btcturk.com/api/v2/ticker";
+/// Default for `BTCTURK_MAX_AGE_SECS`.
+pub const DEFAULT_BTCTURK_MAX_AGE_SECS: u64 = 300;
+const BINANCE_TICKER_URL: &str = "https://api.binance.com/api/v3/ticker/price";
+const COINGECKO_PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+const NOBITEX_USDT_URL: &str =
+    "https://api.nobitex.ir/market/stats?srcCurrency=usdt&dstCurrency=rls";
+
+// (code, name, emoji, BtcTurk pair) — قیمت دلاری‌شون با نرخ دلار به تومان تبدیل می‌شه
+pub const CRYPTO_PAIRS: &[(&str, &str, &str, &str)] = &[
+    ("BTC", "بیت‌کوین", "₿", "BTC_USDT"),
+    ("ETH", "اتریوم", "Ξ", "ETH_USDT"),
+];
+
+// (CoinGecko id, code, name, emoji) برای idهای آشنا؛ بقیه با id به حروف بزرگ نشون داده می‌شن
+const COINGECKO_COINS: &[(&str, &str, &str, &str)] = &[
+    ("bitcoin", "BTC", "بیت‌کوین", "₿"),
+    ("ethereum", "ETH", "اتریوم", "Ξ"),
+    ("tether", "USDT", "تتر", "₮"),
+    ("the-open-network", "TON", "تون‌کوین", "💎"),
+    ("solana", "SOL", "سولانا", "◎"),
+    ("tron", "TRX", "ترون", "🔺"),
+    ("dogecoin", "DOGE", "دوج‌کوین", "🐕"),
+    ("ripple", "XRP", "ریپل", "✖️"),
+];
+
+/// Code, name and emoji shown for a CoinGecko id.
+pub fn coingecko_coin(id: &str) -> (String, String, String) {
+    match COINGECKO_COINS.iter().find(|(i, ..)| *i == id) {
+        Some((_, code, name, emoji)) => (code.to_string(), name.to_string(), emoji.to_string()),
+        None => (id.to_ascii_uppercase(), id.to_string(), "🪙".to_string()),
+    }
+}
+
+// منبع نرخ USDT/TRY؛ ترتیبشون از TRY_SOURCES می‌آد
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrySource {
+    BtcTurk,
+    Binance,
+}
+
+impl TrySource {
+    pub fn label(self) -> &'static str {
+        match self {
+            TrySource::BtcTurk => "BtcTurk",
+            TrySource::Binance => "Binance",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BtcTurkRes {
+    success: bool,
+    // جواب ناموفق data رو null می‌فرسته
+    data: Option<Vec<BtcTurkItem>>,
+}
+
+// {"pair":"USDTTRY","pairNormalized":"USDT_TRY","timestamp":1718000000000,"last":32.4,...}
+#[derive(Deserialize)]
+struct BtcTurkItem {
+    pair: String,
+    // میلی‌ثانیه‌ی یونیکس
+    timestamp: f64,
+    last: f64,
+}
+
+// {"symbol":"USDTTRY","price":"34.12000000"} — قیمت به‌صورت رشته میاد
+#[derive(Deserialize)]
+struct BinancePriceResponse {
+    price: String,
+}
+
+// {"bitcoin":{"usd":67012.5},"ethereum":{"usd":3521.1}}
+#[derive(Deserialize)]
+struct CoinGeckoResponse(HashMap<String, HashMap<String, f64>>);
+
+// {"status":"ok","stats":{"usdt-rls":{"latest":"1026000", ...}}}
+#[derive(Deserialize)]
+struct NobitexStatsResponse {
+    status: String,
+    #[serde(default)]
+    stats: HashMap<String, NobitexMarket>,
+}
+
+#[derive(Deserialize)]
+struct NobitexMarket {
+    latest: Option<NobitexNumber>,
+}
+
+// نوبیتکس قیمت رو گاهی رشته و گاهی عدد برمی‌گردونه
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NobitexNumber {
+    Text(String),
+    Number(f64),
+}
+
+impl NobitexNumber {
+    fn value(&self) -> Option<f64> {
+        match self {
+            NobitexNumber::Text(s) => s.trim().parse().ok(),
+            NobitexNumber::Number(n) => Some(*n),
+        }
+    }
+}
+
+pub fn round_up_to_i64(v: f64) -> i64 {
+    v.ceil() as i64
+}
+
+// مقدار به ریال، یا برای پروفایل‌های دلاری (مثل انس) به سنت
+pub async fn fetch_tgju_rate(
+    client: &Client,
+    url: &str,
+    unit: PriceUnit,
+    selectors: &[Selector],
+) -> Result<(i64, Extraction), FetchError> {
+    let resp = client
+        .get(url)
+        .header(
+            "User-Agent",
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/128.0",
+        )
+        .send()
+        .await
+        .map_err(|source| FetchError::Http {
+            url: url.to_string(),
+            source,
+        })?;
+
+    let body = resp.text().await.map_err(|source| FetchError::Body {
+        url: url.to_string(),
+        source,
+    })?;
+
+    extract_tgju_rate(&body, url, unit, selectors)
+}
+
+/// Normalizes a scraped number to ASCII: Persian/Arabic digits become
+/// `0-9`, `٫` becomes `.`, and thousands separators (`,` `٬`), spaces and
+/// zero-width/direction marks are dropped.
+pub fn clean_number(raw: &str) -> String {
+    raw.chars()
+        .filter_map(|c| match c {
+            '۰'..='۹' => char::from_digit(c as u32 - '۰' as u32, 10),
+            '٠'..='٩' => char::from_digit(c as u32 - '٠' as u32, 10),
+            '٫' => Some('.'),
+            ',' | '٬' | '\u{200c}' | '\u{200d}' | '\u{200e}' | '\u{200f}' | '\u{a0}' => None,
+            c if c.is_whitespace() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Parses a cleaned number with an optional decimal part into an integer
+/// with `decimals` fixed decimal places: `"2318.45"` with 2 → `231845`.
+pub fn parse_fixed_point(clean: &str, decimals: u32) -> Result<i64, FetchError> {
+    let scale = 10_i64.pow(decimals);
+    // f64 خودش "inf" و "NaN" رو هم قبول می‌کنه، پس فقط رقم و نقطه
+    let numeric = clean
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.' || c == '-');
+    let out_of_range = || FetchError::OutOfRange {
+        raw: clean.to_string(),
+    };
+    match clean.parse::<i64>() {
+        Ok(whole) => return whole.checked_mul(scale).ok_or_else(out_of_range),
+        Err(source) if !numeric => {
+            return Err(FetchError::ParseInt {
+                raw: clean.to_string(),
+                source,
+            });
+        }
+        Err(_) => {}
+    }
+    let scaled = clean
+        .parse::<f64>()
+        .map(|v| (v * scale as f64).round())
+        .map_err(|source| FetchError::ParseFloat {
+            raw: clean.to_string(),
+            source,
+        })?;
+    // as i64 بی‌صدا به i64::MAX می‌چسبه
+    if scaled.abs() >= i64::MAX as f64 {
+        return Err(out_of_range());
+    }
+    Ok(scaled as i64)
+}
+
+// قیمت رو از HTML صفحه‌ی پروفایل tgju بیرون می‌کشه؛ ریالی‌ها گرد می‌شن، دلاری‌ها به سنت
+pub fn extract_tgju_rate(
+    body: &str,
+    url: &str,
+    unit: PriceUnit,
+    selectors: &[Selector],
+) -> Result<(i64, Extraction), FetchError> {
+    let doc = Html::parse_document(body);
+
+    // اولین سلکتوری که عنصر غیرخالی پیدا کنه برنده‌ست، بعد regexها
+    let found = selectors
+        .iter()
+        .enumerate()
+        .find_map(|(i, selector)| {
+            let raw: String = doc.select(selector).next()?.text().collect();
+            let how = if i == 0 {
+                Extraction::Primary
+            } else {
+                Extraction::Fallback(i)
+            };
+            (!raw.trim().is_empty()).then_some((raw, how))
+        })
+        .or_else(|| regex_fallback(body, url).map(|raw| (raw.to_string(), Extraction::Regex)));
+    let Some((raw, how)) = found else {
+        return Err(FetchError::SelectorNotFound {
+            url: url.to_string(),
+            selector: format!("{} candidate selectors or price patterns", selectors.len()),
+        });
+    };
+    report_extraction(url, how);
+    let clean = clean_number(&raw);
+    let value = match unit {
+        PriceUnit::Rial => parse_fixed_point(&clean, 0),
+        PriceUnit::Usd | PriceUnit::UsdToman => parse_fixed_point(&clean, 2),
+    }?;
+    Ok((value, how))
+}
+
+// الگوها به ترتیب: span قیمت، JSON همین ابزار، متن «نرخ فعلی»
+fn regex_fallback<'a>(body: &'a str, url: &str) -> Option<&'a str> {
+    let first = |re: &Regex| re.captures(body).and_then(|caps| caps.get(1));
+    first(&TGJU_DATA_COL_PRICE)
+        .map(|m| m.as_str())
+        .or_else(|| embedded_json_price(body, url))
+        .or_else(|| first(&TGJU_LABEL_PRICE).map(|m| m.as_str()))
+}
+
+// از "item" همین صفحه تا "item" بعدی (یا آخر اسکریپت) شیء خودشه
+fn embedded_json_price<'a>(body: &'a str, url: &str) -> Option<&'a str> {
+    let path = url.split(['?', '#']).next()?.trim_end_matches('/');
+    let slug = path.rsplit('/').next()?;
+    let items: Vec<_> = TGJU_JSON_ITEM.captures_iter(body).collect();
+    let own = items.iter().position(|caps| &caps[1] == slug)?;
+    let start = items[own].get(0)?.end();
+    let end = items
+        .get(own + 1)
+        .and_then(|caps| caps.get(0))
+        .map_or(body.len(), |m| m.start());
+    let object = &body[start..end];
+    let object = &object[..object.find("</script").unwrap_or(object.len())];
+    let caps = TGJU_JSON_LAST_TRADE.captures(object)?;
+    Some(caps.get(1)?.as_str())
+}
+
+fn report_extraction(url: &str, how: Extraction) {
+    let Ok(mut missed) = PRIMARY_MISSED.lock() else {
+        return;
+    };
+    if how == Extraction::Primary {
+        if missed.remove(url) {
+            debug!(%url, "✅ سلکتور اصلی tgju دوباره جواب داد");
+        }
+    } else if missed.insert(url.to_string()) {
+        warn!(%url, strategy = %how, "⚠️ سلکتور اصلی tgju جواب نداد؛ احتمالاً قالب صفحه عوض شده");
+    }
+}
+
+// آخرین قیمت یک جفت‌ارز در BtcTurk، مثل USDT_TRY یا BTC_USDT
+pub async fn fetch_btcturk_price(
+    client: &Client,
+    pair: &str,
+    max_age: Duration,
+) -> Result<f64, FetchError> {
+    let url = format!("{}?pairSymbol={}", BTCTURK_TICKER_URL, pair);
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|source| FetchError::Http {
+            url: url.clone(),
+            source,
+        })?;
+    let txt = resp
+        .text()
+        .await
+        .map_err(|source| FetchError::Body { url, source })?;
+
+    parse_btcturk_last(&txt, pair, max_age, unix_now())
+}
+
+/// The `last` price of `pair` (`USDT_TRY` or `USDTTRY`) in a ticker response.
+/// Fails when the pair isn't in the response, listing what was, or when its
+/// timestamp is more than `max_age` before `now`.
+pub fn parse_btcturk_last(
+    txt: &str,
+    pair: &str,
+    max_age: Duration,
+    now: i64,
+) -> Result<f64, FetchError> {
+    let obj: BtcTurkRes = match serde_json::from_str(txt) {
+        Ok(obj) => obj,
+        Err(source) => {
+            return Err(FetchError::JsonDecode {
+                body: txt.to_string(),
+                source,
+            });
+        }
+    };
+    let data = obj.data.unwrap_or_default();
+    if !obj.success || data.is_empty() {
+        return Err(FetchError::UpstreamRejected {
+            source_name: "BTCTurk".to_string(),
+            reason: "success=false or empty data".to_string(),
+        });
+    }
+    // فیلد pair بدون زیرخط میاد، مثل USDTTRY
+    let wanted = pair.replace('_', "");
+    let Some(item) = data
+        .iter()
+        .find(|item| item.pair.eq_ignore_ascii_case(&wanted))
+    else {
+        let returned: Vec<&str> = data.iter().map(|item| item.pair.as_str()).collect();
+        return Err(FetchError::PairNotFound {
+            source_name: "BTCTurk".to_string(),
+            pair: wanted,
+            returned: returned.join(", "),
+        });
+    };
+    let age_secs = now - (item.timestamp / 1000.0) as i64;
+    if age_secs > max_age.as_secs() as i64 {
+        return Err(FetchError::UpstreamRejected {
+            source_name: "BTCTurk".to_string(),
+            reason: format!("a {} ticker {}s old", wanted, age_secs),
+        });
+    }
+    Ok(item.last)
+}
+
+// آخرین قیمت یک نماد در Binance، مثل USDTTRY
+pub async fn fetch_binance_price(client: &Client, symbol: &str) -> Result<f64, FetchError> {
+    let url = format!("{}?symbol={}", BINANCE_TICKER_URL, symbol);
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|source| FetchError::Http {
+            url: url.clone(),
+            source,
+        })?;
+    let txt = resp
+        .text()
+        .await
+        .map_err(|source| FetchError::Body { url, source })?;
+
+    parse_binance_price(&txt)
+}
+
+pub fn parse_binance_price(txt: &str) -> Result<f64, FetchError> {
+    let obj: BinancePriceResponse = match serde_json::from_str(txt) {
+        Ok(obj) => obj,
+        Err(source) => {
+            return Err(FetchError::JsonDecode {
+                body: txt.to_string(),
+                source,
+            });
+        }
+    };
+    match obj.price.parse::<f64>() {
+        Ok(price) if price > 0.0 => Ok(price),
+        _ => Err(FetchError::UpstreamRejected {
+            source_name: "Binance".to_string(),
+            reason: format!("unusable price {:?}", obj.price),
+        }),
+    }
+}
+
+// قیمت دلاری چند رمزارز با یک درخواست؛ بدون id درخواستی فرستاده نمی‌شه
+pub async fn fetch_crypto_usd(
+    client: &Client,
+    ids: &[String],
+) -> Result<HashMap<String, f64>, FetchError> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let url = format!(
+        "{}?ids={}&vs_currencies=usd",
+        COINGECKO_PRICE_URL,
+        ids.join(",")
+    );
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|source| FetchError::Http {
+            url: url.clone(),
+            source,
+        })?;
+    let txt = resp
+        .text()
+        .await
+        .map_err(|source| FetchError::Body { url, source })?;
+
+    parse_coingecko_prices(&txt)
+}
+
+/// USD prices by CoinGecko id. Ids CoinGecko doesn't know are simply absent.
+pub fn parse_coingecko_prices(txt: &str) -> Result<HashMap<String, f64>, FetchError> {
+    let CoinGeckoResponse(coins) = match serde_json::from_str(txt) {
+        Ok(obj) => obj,
+        Err(source) => {
+            return Err(FetchError::JsonDecode {
+                body: txt.to_string(),
+                source,
+            });
+        }
+    };
+    Ok(coins
+        .into_iter()
+        .filter_map(|(id, prices)| {
+            let usd = *prices.get("usd")?;
+            (usd.is_finite() && usd > 0.0).then_some((id, usd))
+        })
+        .collect())
+}
+
+// قیمت تتر در نوبیتکس، به ریال
+pub async fn fetch_nobitex_usdt(client: &Client) -> Result<i64, FetchError> {
+    let resp = client
+        .get(NOBITEX_USDT_URL)
+        .send()
+        .await
+        .map_err(|source| FetchError::Http {
+            url: NOBITEX_USDT_URL.to_string(),
+            source,
+        })?;
+    let txt = resp.text().await.map_err(|source| FetchError::Body {
+        url: NOBITEX_USDT_URL.to_string(),
+        source,
+    })?;
+
+    parse_nobitex_usdt(&txt)
+}
+
+pub fn parse_nobitex_usdt(txt: &str) -> Result<i64, FetchError> {
+    let obj: NobitexStatsResponse = match serde_json::from_str(txt) {
+        Ok(obj) => obj,
+        Err(source) => {
+            return Err(FetchError::JsonDecode {
+                body: txt.to_string(),
+                source,
+            });
+        }
+    };
+    let latest = obj
+        .stats
+        .get("usdt-rls")
+        .and_then(|m| m.latest.as_ref())
+        .and_then(NobitexNumber::value);
+    match latest {
+        Some(rial) if obj.status == "ok" && rial > 0.0 => Ok(round_up_to_i64(rial)),
+        _ => Err(FetchError::UpstreamRejected {
+            source_name: "Nobitex".to_string(),
+            reason: format!("status={} without a usable usdt-rls price", obj.status),
+        }),
+    }
+}
+
+// منابع رو به ترتیب امتحان می‌کنه و اولین جواب موفق رو برمی‌گردونه
+pub async fn fetch_usdt_try(
+    client: &Client,
+    sources: &[TrySource],
+    btcturk_max_age: Duration,
+) -> Result<(f64, TrySource), BotError> {
+    let mut last_err = None;
+    for &source in sources {
+        let result = retry_with_backoff(
+            || async move {
+                match source {
+                    TrySource::BtcTurk => {
+                        fetch_btcturk_price(client, "USDT_TRY", btcturk_max_age).await
+                    }
+                    TrySource::Binance => fetch_binance_price(client, "USDTTRY").await,
+                }
+                .map_err(BotError::from)
+            },
+            MAX_ATTEMPTS,
+            RETRY_BASE_DELAY,
+        )
+        .await;
+        match result {
+            Ok(rate) => return Ok((rate, source)),
+            Err(e) => {
+                warn!(source = source.label(), error = %e, "⚠️ دریافت USDT/TRY ناموفق");
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(last_err.expect("TRY_SOURCES is never empty"))
+}
+
+/// What a fetched [`Rate`] is quoted in: whole rials from tgju and Nobitex,
+/// or dollars from tgju's dollar-priced pages and BtcTurk.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Quote {
+    Rial(Rial),
+    Usd(f64),
+}
+
+/// One value fetched from a [`RateSource`].
+#[derive(Clone, Debug)]
+pub struct Rate {
+    pub currency: Currency,
+    pub quote: Quote,
+    pub fetched_at: i64,
+}
+
+impl Rate {
+    fn now(currency: Currency, quote: Quote) -> Rate {
+        Rate {
+            currency,
+            quote,
+            fetched_at: unix_now(),
+        }
+    }
+
+    /// The rate in rial, when it is quoted in rial.
+    pub fn rial(&self) -> Option<Rial> {
+        match self.quote {
+            Quote::Rial(rial) => Some(rial),
+            Quote::Usd(_) => None,
+        }
+    }
+
+    /// The rate in dollars, when it is quoted in dollars.
+    pub fn usd(&self) -> Option<f64> {
+        match self.quote {
+            Quote::Usd(usd) => Some(usd),
+            Quote::Rial(_) => None,
+        }
+    }
+
+    /// Value in toman, see [`Rial::toman`].
+    pub fn toman(&self) -> Option<i64> {
+        self.rial().map(Rial::toman)
+    }
+}
+
+impl fmt::Display for Rate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quote {
+            Quote::Rial(rial) => write!(f, "{} rial", fmt_int(rial.0)),
+            Quote::Usd(usd) => write!(f, "${:.2}", usd),
+        }
+    }
+}
+
+/// A provider of a single rate. New providers implement this and get
+/// registered in [`build_rate_sources`]; the main loop doesn't change.
+#[async_trait]
+pub trait RateSource: Send + Sync {
+    /// Currency code of the rate this source produces, e.g. `USD` or `BTC`.
+    fn name(&self) -> &str;
+
+    async fn fetch(&self, client: &Client) -> Result<Rate, FetchError>;
+}
+
+/// A tgju profile page from the currency config.
+pub struct TgjuSource {
+    currency: CurrencyConfig,
+    selectors: Vec<Selector>,
+}
+
+#[async_trait]
+impl RateSource for TgjuSource {
+    fn name(&self) -> &str {
+        self.currency.code.code()
+    }
+
+    async fn fetch(&self, client: &Client) -> Result<Rate, FetchError> {
+        let c = &self.currency;
+        let (v, how) = fetch_tgju_rate(client, &c.url, c.unit, &self.selectors).await?;
+        debug!(currency = %c.code, strategy = %how, "tgju price extracted");
+        Ok(match c.unit {
+            PriceUnit::Rial => Rate::now(c.code.clone(), Quote::Rial(Rial(v))),
+            PriceUnit::Usd | PriceUnit::UsdToman => {
+                Rate::now(c.code.clone(), Quote::Usd(v as f64 / 100.0))
+            }
+        })
+    }
+}
+
+/// A BtcTurk `*_USDT` ticker pair such as `BTC_USDT`, taken as dollars.
+pub struct BtcTurkSource {
+    code: String,
+    pair: String,
+    max_age: Duration,
+}
+
+#[async_trait]
+impl RateSource for BtcTurkSource {
+    fn name(&self) -> &str {
+        &self.code
+    }
+
+    async fn fetch(&self, client: &Client) -> Result<Rate, FetchError> {
+        let last = fetch_btcturk_price(client, &self.pair, self.max_age).await?;
+        Ok(Rate::now(
+            Currency::from(self.code.as_str()),
+            Quote::Usd(last),
+        ))
+    }
+}
+
+/// Nobitex's USDT/IRR market.
+pub struct NobitexUsdtSource;
+
+#[async_trait]
+impl RateSource for NobitexUsdtSource {
+    fn name(&self) -> &str {
+        "USDT"
+    }
+
+    async fn fetch(&self, client: &Client) -> Result<Rate, FetchError> {
+        let rial = fetch_nobitex_usdt(client).await?;
+        Ok(Rate::now(Currency::Usdt, Quote::Rial(Rial(rial))))
+    }
+}
+
+/// The sources fetched each cycle, in registration order, plus the
+/// USDT/TRY fallback chain.
+pub struct SourceRegistry {
+    sources: Vec<Box<dyn RateSource>>,
+    try_chain: Vec<TrySource>,
+    // تیکر BtcTurk قدیمی‌تر از این رد می‌شه
+    btcturk_max_age: Duration,
+}
+
+impl SourceRegistry {
+    /// An empty registry; `try_chain` must not be empty.
+    pub fn new(try_chain: Vec<TrySource>, btcturk_max_age: Duration) -> SourceRegistry {
+        SourceRegistry {
+            sources: Vec::new(),
+            try_chain,
+            btcturk_max_age,
+        }
+    }
+
+    /// Adds a source. A second source with the same name is refused, since
+    /// results are keyed by name.
+    pub fn register(&mut self, source: Box<dyn RateSource>) -> Result<(), String> {
+        if self.sources.iter().any(|s| s.name() == source.name()) {
+            return Err(format!("Duplicate rate source for {}", source.name()));
+        }
+        self.sources.push(source);
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn RateSource> {
+        self.sources.iter().map(|s| s.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// USDT/TRY sources in the order they're tried.
+    pub fn try_chain(&self) -> &[TrySource] {
+        &self.try_chain
+    }
+
+    /// Lira rate from the first source in the chain that answers.
+    pub async fn fetch_usdt_try(&self, client: &Client) -> Result<(f64, TrySource), BotError> {
+        fetch_usdt_try(client, &self.try_chain, self.btcturk_max_age).await
+    }
+}
+
+/// Every source fetched each cycle: the configured tgju currencies, tgju's
+/// direct lira page (unless the config already has a TRY entry), the crypto
+/// pairs and Nobitex tether, with `try_sources` as the USDT/TRY chain.
+/// BtcTurk tickers older than `btcturk_max_age` are rejected.
+/// Fails on a `selector` override that doesn't parse or a duplicate code.
+pub fn build_rate_sources(
+    currencies: &[CurrencyConfig],
+    try_sources: Vec<TrySource>,
+    btcturk_max_age: Duration,
+) -> Result<SourceRegistry, String> {
+    let mut registry = SourceRegistry::new(try_sources, btcturk_max_age);
+    for c in currencies {
+        let selectors = tgju_selectors(c).map_err(|e| format!("Currency {}: {}", c.code, e))?;
+        registry.register(Box::new(TgjuSource {
+            currency: c.clone(),
+            selectors,
+        }))?;
+    }
+    if !currencies.iter().any(|c| c.code == Currency::Try) {
+        let lira = tgju_lira_currency();
+        let selectors = tgju_selectors(&lira).map_err(|e| format!("Currency TRY: {}", e))?;
+        registry.register(Box::new(TgjuSource {
+            currency: lira,
+            selectors,
+        }))?;
+    }
+    for (code, _, _, pair) in CRYPTO_PAIRS {
+        registry.register(Box::new(BtcTurkSource {
+            code: code.to_string(),
+            pair: pair.to_string(),
+            max_age: btcturk_max_age,
+        }))?;
+    }
+    registry.register(Box::new(NobitexUsdtSource))?;
+    Ok(registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Unit;
+
+    const USD_PAGE: &str = include_str!("../tests/fixtures/tgju_price_dollar_rl.html");
+    const GBP_PAGE: &str = include_str!("../tests/fixtures/tgju_price_gbp.html");
+    const KWD_PAGE: &str = include_str!("../tests/fixtures/tgju_price_kwd.html");
+    const IQD_PAGE: &str = include_str!("../tests/fixtures/tgju_sana_sell_iqd.html");
+    const ALTERNATE_PAGE: &str = include_str!("../tests/fixtures/tgju_alternate_layout.html");
+    const SCRIPT_PAGE: &str = include_str!("../tests/fixtures/tgju_script_layout.html");
+    const TICKER_FIRST_PAGE: &str = include_str!("../tests/fixtures/tgju_script_ticker_first.html");
+    const BTCTURK_OK: &str = include_str!("../tests/fixtures/btcturk_usdt_try.json");
+    const BTCTURK_FAILURE: &str = include_str!("../tests/fixtures/btcturk_failure.json");
+    const BTCTURK_EMPTY: &str = include_str!("../tests/fixtures/btcturk_empty.json");
+    const BTCTURK_CRYPTO: &str = include_str!("../tests/fixtures/btcturk_crypto_usdt.json");
+
+    // زمان تیکر داخل فیکسچر، به ثانیه
+    const TICKER_AT: i64 = 1_718_000_000;
+    const MAX_AGE: Duration = Duration::from_secs(DEFAULT_BTCTURK_MAX_AGE_SECS);
+
+    #[test]
+    fn round_up_to_i64_rounds_towards_positive_infinity() {
+        assert_eq!(round_up_to_i64(1025400.0), 1025400);
+        assert_eq!(round_up_to_i64(1025400.01), 1025401);
+        assert_eq!(round_up_to_i64(0.2), 1);
+        assert_eq!(round_up_to_i64(-0.5), 0);
+    }
+
+    #[test]
+    fn clean_number_normalizes_raw_tgju_text() {
+        assert_eq!(clean_number("1,025,400"), "1025400");
+        assert_eq!(clean_number("۱٬۰۲۵٬۴۰۰"), "1025400");
+        assert_eq!(clean_number("۱,۰۲۵,۴۰۰"), "1025400");
+        assert_eq!(clean_number("١٬٠٢٥٬٤٠٠"), "1025400");
+        assert_eq!(clean_number("۲٬۳۱۸٫۴۵"), "2318.45");
+        // فاصله، nbsp، نیم‌فاصله و علامت‌های جهت متن که tgju دور عدد می‌ذاره
+        assert_eq!(
+            clean_number("\n    \u{200f}1,025,400\u{200c}\u{a0}"),
+            "1025400"
+        );
+        assert_eq!(clean_number("\u{200e}2,318.45\u{200d} "), "2318.45");
+    }
+
+    #[test]
+    fn parse_fixed_point_scales_decimals() {
+        assert_eq!(parse_fixed_point("1025400", 0).unwrap(), 1_025_400);
+        assert_eq!(parse_fixed_point("2318.45", 2).unwrap(), 231_845);
+        assert_eq!(parse_fixed_point("2318", 2).unwrap(), 231_800);
+        assert_eq!(parse_fixed_point("2318.4", 2).unwrap(), 231_840);
+        // رقم اضافه گرد می‌شه
+        assert_eq!(parse_fixed_point("2318.456", 2).unwrap(), 231_846);
+        assert_eq!(parse_fixed_point("1025400.6", 0).unwrap(), 1_025_401);
+    }
+
+    #[test]
+    fn parse_fixed_point_rejects_non_numbers() {
+        assert!(matches!(
+            parse_fixed_point("", 0),
+            Err(FetchError::ParseFloat { .. })
+        ));
+        assert!(matches!(
+            parse_fixed_point("inf", 0),
+            Err(FetchError::ParseInt { .. })
+        ));
+        assert!(matches!(
+            parse_fixed_point("NaN", 2),
+            Err(FetchError::ParseInt { .. })
+        ));
+        assert!(matches!(
+            parse_fixed_point("1.2.3", 2),
+            Err(FetchError::ParseFloat { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_fixed_point_rejects_overflow() {
+        // ۱۹ رقم در i64 جا می‌شه ولی ضربش در ۱۰۰ نه
+        assert!(matches!(
+            parse_fixed_point("1234567890123456789", 2),
+            Err(FetchError::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            parse_fixed_point("9999999999999999999.5", 0),
+            Err(FetchError::OutOfRange { .. })
+        ));
+        assert_eq!(
+            parse_fixed_point("1234567890123456789", 0).unwrap(),
+            1_234_567_890_123_456_789
+        );
+    }
+
+    #[test]
+    fn raw_tgju_strings_end_to_end() {
+        let parse = |raw: &str, decimals| parse_fixed_point(&clean_number(raw), decimals).unwrap();
+        assert_eq!(parse("۱٬۰۲۵٬۴۰۰", 0), 1_025_400);
+        assert_eq!(parse("۲٬۳۱۸٫۴۵", 2), 231_845);
+        assert_eq!(parse("2,318.45\u{200c}", 2), 231_845);
+    }
+
+    #[test]
+    fn extracts_rial_value_from_saved_page() {
+        let (rial, how) = extract_tgju_rate(
+            USD_PAGE,
+            "https://www.tgju.org/profile/price_dollar_rl",
+            PriceUnit::Rial,
+            &DEFAULT_TGJU_SELECTORS,
+        )
+        .unwrap();
+        assert_eq!(rial, 1_025_400);
+        assert_eq!(how, Extraction::Primary);
+    }
+
+    fn extract(body: &str, selectors: &[Selector]) -> (i64, Extraction) {
+        extract_tgju_rate(
+            body,
+            "https://www.tgju.org/profile/price_dollar_rl",
+            PriceUnit::Rial,
+            selectors,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn alternate_layout_falls_back_to_data_col_selector() {
+        assert_eq!(
+            extract(ALTERNATE_PAGE, &DEFAULT_TGJU_SELECTORS),
+            (1_026_100, Extraction::Fallback(1))
+        );
+    }
+
+    #[test]
+    fn script_only_layout_falls_back_to_regex() {
+        assert_eq!(
+            extract(SCRIPT_PAGE, &DEFAULT_TGJU_SELECTORS),
+            (1_027_300, Extraction::Regex)
+        );
+    }
+
+    #[test]
+    fn configured_selector_is_tried_first() {
+        let c = CurrencyConfig {
+            selector: Some(".summary-item .value".to_string()),
+            ..tgju_lira_currency()
+        };
+        let selectors = tgju_selectors(&c).unwrap();
+        assert_eq!(selectors.len(), TGJU_PRICE_SELECTORS.len() + 1);
+        assert_eq!(
+            extract(ALTERNATE_PAGE, &selectors),
+            (1_026_100, Extraction::Primary)
+        );
+        // روی قالب فعلی سلکتور سفارشی چیزی پیدا نمی‌کنه و سلکتور پیش‌فرض اول برنده‌ست
+        assert_eq!(
+            extract(USD_PAGE, &selectors),
+            (1_025_400, Extraction::Fallback(1))
+        );
+    }
+
+    #[test]
+    fn embedded_json_is_read_from_the_pages_own_item() {
+        assert_eq!(
+            extract(TICKER_FIRST_PAGE, &DEFAULT_TGJU_SELECTORS),
+            (1_027_300, Extraction::Regex)
+        );
+        let gbp = "https://www.tgju.org/profile/price_gbp/";
+        assert_eq!(
+            embedded_json_price(TICKER_FIRST_PAGE, gbp),
+            Some("1,346,200")
+        );
+        // ابزاری که در JSON نیست چیزی نمی‌گیره، نه "p" اولین ابزار
+        let aed = "https://www.tgju.org/profile/price_aed";
+        assert_eq!(embedded_json_price(TICKER_FIRST_PAGE, aed), None);
+    }
+
+    #[test]
+    fn price_patterns_match_in_order() {
+        let url = "https://www.tgju.org/profile/price_dollar_rl";
+        let span = r#"<span data-col="info.last_trade.PDrCotVal" class="x">۱,۰۲۵,۴۰۰</span>"#;
+        let json = r#"{"item":"price_dollar_rl","info":{"last_trade":{"p":"1,026,100"}}}"#;
+        let label = "<td>نرخ فعلی</td><td>1,027,300</td>";
+        assert_eq!(regex_fallback(span, url), Some("۱,۰۲۵,۴۰۰"));
+        assert_eq!(regex_fallback(json, url), Some("1,026,100"));
+        assert_eq!(regex_fallback(label, url), Some("1,027,300"));
+        assert_eq!(
+            regex_fallback(&format!("{label}{json}"), url),
+            Some("1,026,100")
+        );
+    }
+
+    #[test]
+    fn invalid_configured_selector_is_reported() {
+        let c = CurrencyConfig {
+            selector: Some("div[".to_string()),
+            ..tgju_lira_currency()
+        };
+        assert!(matches!(
+            tgju_selectors(&c),
+            Err(FetchError::InvalidSelector { .. })
+        ));
+    }
+
+    #[test]
+    fn gbp_rial_value_from_saved_page() {
+        let (rial, how) = extract(GBP_PAGE, &DEFAULT_TGJU_SELECTORS);
+        assert_eq!(rial, 1_346_200);
+        assert_eq!(how, Extraction::Primary);
+        assert_eq!(Rial(rial).toman(), 134_620);
+    }
+
+    #[test]
+    fn kwd_is_extracted_and_converted() {
+        let (rial, how) = extract(KWD_PAGE, &DEFAULT_TGJU_SELECTORS);
+        assert_eq!((rial, how), (3_343_100, Extraction::Primary));
+        // دینار کویت از دلار گرون‌تره ولی تا تومان هیچ سرریزی نداره
+        assert_eq!(Rial(rial).toman(), 334_310);
+        assert_eq!(round_up_to_i64(rial as f64 / 10.0), 334_310);
+    }
+
+    #[test]
+    fn iqd_is_extracted_and_scaled_per_unit() {
+        let (rial, how) = extract(IQD_PAGE, &DEFAULT_TGJU_SELECTORS);
+        assert_eq!((rial, how), (783, Extraction::Primary));
+        // بدون اعشار ۷۸ تومان می‌شد؛ با RATE_IQD_DECIMAL_PLACES=1 همون ۷۸.۳ می‌مونه
+        let price = Rial(rial);
+        assert_eq!(price.toman(), 78);
+        let scaled = price.toman_scaled(1);
+        assert_eq!(scaled, 783);
+        assert_eq!(Unit::TomanDecimals(1).amount(scaled), "78.3");
+        assert_eq!(Unit::TomanDecimals(1).whole(scaled), 78);
+    }
+
+    #[tokio::test]
+    async fn fetch_tgju_rate_reads_the_served_page() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route(
+            "/profile/price_gbp",
+            axum::routing::get(|| async { axum::response::Html(GBP_PAGE) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let url = format!("http://{}/profile/price_gbp", addr);
+        // پراکسی سیستم نباید درخواست localhost رو بگیره
+        let client = Client::builder().no_proxy().build().unwrap();
+        let (rial, _) = fetch_tgju_rate(&client, &url, PriceUnit::Rial, &DEFAULT_TGJU_SELECTORS)
+            .await
+            .unwrap();
+        assert_eq!(rial, 1_346_200);
+    }
+
+    #[test]
+    fn page_without_a_price_is_selector_not_found() {
+        let err = extract_tgju_rate(
+            "<html><body><p>503 Service Unavailable</p></body></html>",
+            "https://www.tgju.org/profile/price_dollar_rl",
+            PriceUnit::Rial,
+            &DEFAULT_TGJU_SELECTORS,
+        )
+        .unwrap_err();
+        assert!(matches!(err, FetchError::SelectorNotFound { .. }), "{err}");
+    }
+
+    #[test]
+    fn btcturk_last_from_success_response() {
+        let last = parse_btcturk_last(BTCTURK_OK, "USDT_TRY", MAX_AGE, TICKER_AT + 10).unwrap();
+        assert_eq!(last, 32.41);
+    }
+
+    #[test]
+    fn btcturk_success_false_is_rejected() {
+        let err = parse_btcturk_last(BTCTURK_FAILURE, "USDT_TRY", MAX_AGE, TICKER_AT).unwrap_err();
+        assert!(matches!(err, FetchError::UpstreamRejected { .. }), "{err}");
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn btcturk_empty_data_is_rejected() {
+        let err = parse_btcturk_last(BTCTURK_EMPTY, "USDT_TRY", MAX_AGE, TICKER_AT).unwrap_err();
+        assert!(matches!(err, FetchError::UpstreamRejected { .. }), "{err}");
+    }
+
+    #[test]
+    fn btcturk_last_of_the_requested_pair() {
+        let last = |pair| parse_btcturk_last(BTCTURK_CRYPTO, pair, MAX_AGE, TICKER_AT).unwrap();
+        assert_eq!(last("BTC_USDT"), 67012.5);
+        assert_eq!(last("ETH_USDT"), 3521.1);
+        assert_eq!(last("ethusdt"), 3521.1);
+    }
+
+    #[test]
+    fn btcturk_missing_pair_lists_what_was_returned() {
+        let err = parse_btcturk_last(BTCTURK_CRYPTO, "USDT_TRY", MAX_AGE, TICKER_AT).unwrap_err();
+        match err {
+            FetchError::PairNotFound { pair, returned, .. } => {
+                assert_eq!(pair, "USDTTRY");
+                assert_eq!(returned, "BTCUSDT, ETHUSDT");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
+    #[test]
+    fn btcturk_stale_ticker_is_rejected() {
+        let max_age = MAX_AGE.as_secs() as i64;
+        assert!(parse_btcturk_last(BTCTURK_OK, "USDT_TRY", MAX_AGE, TICKER_AT + max_age).is_ok());
+        let err = parse_btcturk_last(BTCTURK_OK, "USDT_TRY", MAX_AGE, TICKER_AT + max_age + 1)
+            .unwrap_err();
+        assert!(matches!(err, FetchError::UpstreamRejected { .. }), "{err}");
+    }
+
+    #[test]
+    fn btcturk_body_that_isnt_json_is_a_decode_error() {
+        let err =
+            parse_btcturk_last("<html>502</html>", "USDT_TRY", MAX_AGE, TICKER_AT).unwrap_err();
+        assert!(matches!(err, FetchError::JsonDecode { .. }), "{err}");
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::message::PreviousRates;
+use crate::summary::DailySummary;
+
+/// Small bits of state that need to survive a restart.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BotState {
+    // chat_id -> message_id پیامی که در حالت edit ویرایش می‌شه
+    #[serde(default)]
+    pub message_ids: HashMap<String, i64>,
+    // آخرین update_id پردازش‌شده تا بعد از ری‌استارت دستورها دوباره جواب داده نشن
+    #[serde(default)]
+    pub last_update_id: Option<i64>,
+    // روز (به وقت تهران) آخرین خلاصه‌ی روزانه، تا بعد از ری‌استارت دوباره فرستاده نشه
+    #[serde(default)]
+    pub last_summary_date: Option<NaiveDate>,
+    // مقدارهای آخرین پست، برای فلش‌ها و تشخیص بدون تغییر بعد از ری‌استارت (و در --once)
+    #[serde(default)]
+    pub last_posted: PreviousRates,
+    // زمان آخرین پست، تا FORCE_POST_EVERY بعد از ری‌استارت از صفر شروع نشه
+    #[serde(default)]
+    pub last_post_at: Option<DateTime<Utc>>,
+    // بالاترین و پایین‌ترین امروز؛ اگه مال روز دیگه‌ای باشه کنار گذاشته می‌شه
+    #[serde(default)]
+    pub daily: Option<DailySummary>,
+    // چت‌های خصوصی که با /subscribe هر پست رو جدا می‌گیرن
+    #[serde(default)]
+    pub subscribers: Vec<i64>,
+}
+
+/// The store shared by the posting loop and the command poller.
+pub type SharedState = Arc<Mutex<StateStore>>;
+
+/// [`BotState`] together with the file it's persisted to.
+pub struct StateStore {
+    path: String,
+    pub state: BotState,
+}
+
+impl StateStore {
+    pub fn load(path: &str) -> StateStore {
+        let state = match fs::read_to_string(path) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                warn!(path = %path, error = %e, "⚠️ فایل وضعیت خراب است، از اول شروع می‌کنیم");
+                BotState::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => BotState::default(),
+            Err(e) => {
+                warn!(path = %path, error = %e, "⚠️ خواندن فایل وضعیت ناموفق");
+                BotState::default()
+            }
+        };
+        StateStore {
+            path: path.to_string(),
+            state,
+        }
+    }
+
+    /// Writes the state to a temp file next to it and renames it over the
+    /// old one, so a crash mid-write leaves the previous file intact.
+    pub fn save(&self) -> Result<(), String> {
+        let raw = serde_json::to_string_pretty(&self.state)
+            .map_err(|e| format!("State encode error: {}", e))?;
+        let tmp = format!("{}.tmp", self.path);
+        fs::write(&tmp, raw).map_err(|e| format!("Write state error for {}: {}", tmp, e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| format!("Write state error for {}: {}", self.path, e))
+    }
+}
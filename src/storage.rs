@@ -0,0 +1,194 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Row, SqlitePool};
+
+use crate::error::BotError;
+
+// هر مهاجرت یک بار اجرا می‌شه و شماره‌اش در PRAGMA user_version ذخیره می‌شه
+const MIGRATIONS: &[&str] = &["CREATE TABLE IF NOT EXISTS rates (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        currency TEXT NOT NULL,
+        value_toman INTEGER NOT NULL,
+        fetched_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS rates_currency_fetched_at ON rates (currency, fetched_at);"];
+
+/// Rate history in SQLite, one row per currency per cycle.
+pub struct Storage {
+    pool: SqlitePool,
+}
+
+/// A stored rate, newest first when returned by [`Storage::get_last_n_rates`].
+#[derive(Debug, Clone)]
+pub struct RateRecord {
+    pub currency: String,
+    pub value_toman: i64,
+    pub fetched_at: i64,
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+impl Storage {
+    pub async fn open(path: &str) -> Result<Storage, BotError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await?;
+
+        let storage = Storage { pool };
+        storage.migrate().await?;
+        Ok(storage)
+    }
+
+    async fn migrate(&self) -> Result<(), BotError> {
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await?;
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let mut tx = self.pool.begin().await?;
+            if i == 0 {
+                // جدول قدیمی (ts, currency, value_rial) قبل از ساخت جدول جدید کنار گذاشته می‌شه
+                let legacy: i64 = sqlx::query_scalar(
+                    "SELECT COUNT(*) FROM pragma_table_info('rates') WHERE name = 'value_rial'",
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                if legacy > 0 {
+                    sqlx::query("ALTER TABLE rates RENAME TO rates_legacy")
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                sqlx::raw_sql(migration).execute(&mut *tx).await?;
+                if legacy > 0 {
+                    sqlx::raw_sql(
+                        "INSERT INTO rates (currency, value_toman, fetched_at)
+                            SELECT currency, value_rial / 10, ts FROM rates_legacy ORDER BY ts;
+                        DROP TABLE rates_legacy;",
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            } else {
+                sqlx::raw_sql(migration).execute(&mut *tx).await?;
+            }
+            // PRAGMA پارامتر bind قبول نمی‌کنه
+            sqlx::query(&format!("PRAGMA user_version = {}", i + 1))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn insert_rates(
+        &self,
+        fetched_at: i64,
+        rates: &[(&str, i64)],
+    ) -> Result<(), BotError> {
+        let mut tx = self.pool.begin().await?;
+        for (currency, value_toman) in rates {
+            sqlx::query("INSERT INTO rates (currency, value_toman, fetched_at) VALUES (?, ?, ?)")
+                .bind(currency)
+                .bind(value_toman)
+                .bind(fetched_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The `n` most recent rows for `currency`, newest first.
+    pub async fn get_last_n_rates(
+        &self,
+        currency: &str,
+        n: usize,
+    ) -> Result<Vec<RateRecord>, BotError> {
+        let rows = sqlx::query(
+            "SELECT currency, value_toman, fetched_at FROM rates
+             WHERE currency = ? ORDER BY fetched_at DESC, id DESC LIMIT ?",
+        )
+        .bind(currency)
+        .bind(n as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RateRecord {
+                currency: row.get("currency"),
+                value_toman: row.get("value_toman"),
+                fetched_at: row.get("fetched_at"),
+            })
+            .collect())
+    }
+
+    /// Rows for `currency` fetched at or after `since`, oldest first.
+    pub async fn get_rates_since(
+        &self,
+        currency: &str,
+        since: i64,
+    ) -> Result<Vec<RateRecord>, BotError> {
+        let rows = sqlx::query(
+            "SELECT currency, value_toman, fetched_at FROM rates
+             WHERE currency = ? AND fetched_at >= ? ORDER BY fetched_at, id",
+        )
+        .bind(currency)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RateRecord {
+                currency: row.get("currency"),
+                value_toman: row.get("value_toman"),
+                fetched_at: row.get("fetched_at"),
+            })
+            .collect())
+    }
+
+    /// Lowest and highest value of `currency` over the past 365 days, or
+    /// `None` if there is no history for it yet.
+    pub async fn get_yearly_extremes(
+        &self,
+        currency: &str,
+    ) -> Result<Option<(i64, i64)>, BotError> {
+        let since = unix_now() - 365 * 86_400;
+        // از ایندکس (currency, fetched_at) استفاده می‌کنه
+        let row = sqlx::query(
+            "SELECT MIN(value_toman) AS low, MAX(value_toman) AS high FROM rates
+             WHERE currency = ? AND fetched_at >= ?",
+        )
+        .bind(currency)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let low: Option<i64> = row.get("low");
+        let high: Option<i64> = row.get("high");
+        Ok(low.zip(high))
+    }
+
+    /// Waits for in-flight queries and closes the pool.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Deletes rows older than `days` and returns how many were removed.
+    pub async fn prune_older_than(&self, days: u32) -> Result<u64, BotError> {
+        let cutoff = unix_now() - i64::from(days) * 86_400;
+        let result = sqlx::query("DELETE FROM rates WHERE fetched_at < ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
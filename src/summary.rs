@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::message::{NumberStyle, ParseMode, RateLine, Unit, fmt_pct, to_fa_digits};
+
+/// Timezone the day boundary and `SUMMARY_TIME` are measured in.
+pub const SUMMARY_TZ: Tz = chrono_tz::Asia::Tehran;
+
+/// Open/close/high/low of one currency over the day.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DayRange {
+    pub code: String,
+    pub emoji: String,
+    pub name: String,
+    pub unit: Unit,
+    pub open: i64,
+    pub close: i64,
+    pub high: i64,
+    pub low: i64,
+}
+
+/// Per-day aggregates, reset when the Tehran date changes. Kept in the state
+/// file so a restart mid-day doesn't lose the day's high and low.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    // روز اولی که ربات وسطش روشن شده کامل نیست
+    pub partial: bool,
+    pub ranges: Vec<DayRange>,
+}
+
+impl DailySummary {
+    /// A summary for the day of `now`, marked partial since the bot starts mid-day.
+    pub fn starting(now: DateTime<Tz>) -> DailySummary {
+        DailySummary {
+            date: now.date_naive(),
+            partial: true,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Folds this cycle's lines into the day. Stale lines are skipped.
+    pub fn record<'a>(&mut self, now: DateTime<Tz>, lines: impl IntoIterator<Item = &'a RateLine>) {
+        let today = now.date_naive();
+        if today != self.date {
+            self.date = today;
+            self.partial = false;
+            self.ranges.clear();
+        }
+        for line in lines.into_iter().filter(|l| l.is_fresh()) {
+            match self
+                .ranges
+                .iter_mut()
+                .find(|r| line.currency == r.code.as_str())
+            {
+                Some(r) => {
+                    r.close = line.value;
+                    r.high = r.high.max(line.value);
+                    r.low = r.low.min(line.value);
+                }
+                None => self.ranges.push(DayRange {
+                    code: line.currency.to_string(),
+                    emoji: line.emoji.clone(),
+                    name: line.name.clone(),
+                    unit: line.unit,
+                    open: line.value,
+                    close: line.value,
+                    high: line.value,
+                    low: line.value,
+                }),
+            }
+        }
+    }
+
+    /// Today's high and low of each currency in `codes`, one line each, for
+    /// the footer of a regular post. Currencies not seen today are skipped.
+    pub fn range_footer(&self, style: NumberStyle, codes: &[&str]) -> Option<String> {
+        let lines: Vec<String> = codes
+            .iter()
+            .filter_map(|code| self.ranges.iter().find(|r| r.code == *code))
+            .map(|r| {
+                format!(
+                    "{} 📈 بالاترین: {} | 📉 پایین‌ترین: {}",
+                    r.emoji,
+                    style.apply(&r.unit.amount(r.high)),
+                    style.apply(&r.unit.amount(r.low))
+                )
+            })
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        Some(format!("📊 دامنه‌ی امروز:\n{}", lines.join("\n")))
+    }
+
+    /// The stored summary if it's still for the day of `now`, otherwise a
+    /// fresh partial one.
+    pub fn resume(stored: Option<DailySummary>, now: DateTime<Tz>) -> DailySummary {
+        stored
+            .filter(|d| d.date == now.date_naive())
+            .unwrap_or_else(|| DailySummary::starting(now))
+    }
+
+    /// True once `now` has passed `at` on a day whose summary wasn't sent yet.
+    pub fn is_due(&self, now: DateTime<Tz>, at: NaiveTime, last_sent: Option<NaiveDate>) -> bool {
+        now.date_naive() == self.date
+            && now.time() >= at
+            && last_sent != Some(self.date)
+            && !self.ranges.is_empty()
+    }
+}
+
+/// The summary post. `yearly` holds the 365-day (low, high) per code from the
+/// history; currencies without an entry just don't get that line.
+pub fn format_summary(
+    mode: ParseMode,
+    style: NumberStyle,
+    summary: &DailySummary,
+    yearly: &HashMap<String, (i64, i64)>,
+) -> String {
+    let date = to_fa_digits(&summary.date.format("%Y-%m-%d").to_string());
+    let mut text = mode.bold(&format!("📅 خلاصه‌ی روز {}", date));
+    text.push('\n');
+    if summary.partial {
+        text.push_str(&mode.italic("(از زمان شروع ربات)"));
+        text.push('\n');
+    }
+    for r in &summary.ranges {
+        text.push('\n');
+        text.push_str(&format!("{} {}\n", mode.text(&r.emoji), mode.bold(&r.name)));
+        text.push_str(&mode.text(&format!(
+            "باز: {} | بسته: {}{}\n📈 بالاترین: {} | 📉 پایین‌ترین: {} {}\n",
+            style.apply(&r.unit.amount(r.open)),
+            style.apply(&r.unit.amount(r.close)),
+            style.apply(&fmt_pct(r.close - r.open, r.open)),
+            style.apply(&r.unit.amount(r.high)),
+            style.apply(&r.unit.amount(r.low)),
+            r.unit.label()
+        )));
+        if let Some((low, high)) = yearly.get(&r.code) {
+            text.push_str(&mode.text(&format!(
+                "سالانه: کف {} — سقف {}\n",
+                style.apply(&r.unit.amount(*low)),
+                style.apply(&r.unit.amount(*high))
+            )));
+        }
+    }
+    text
+}
@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use crate::message::{Message, NumberStyle, Section};
+use crate::template::MessageTemplate;
+
+/// One `[[targets]]` entry in the bot config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetConfig {
+    pub chat_id: String,
+    // جای MESSAGE_TEMPLATE برای همین کانال، مثلاً با برچسب‌های ترکی
+    pub template: Option<String>,
+    // فقط همین کدها؛ نبودنش یعنی همه
+    pub currencies: Option<Vec<String>>,
+    pub number_style: Option<String>,
+}
+
+/// A chat the post goes to, with its own layout and currency list.
+/// Unset fields fall back to the global settings.
+pub struct Target {
+    pub chat_id: String,
+    pub template: Option<MessageTemplate>,
+    pub currencies: Option<Vec<String>>,
+    pub number_style: Option<NumberStyle>,
+}
+
+impl Target {
+    /// A target with no settings of its own, as from `CHANNEL_IDS`.
+    pub fn plain(chat_id: &str) -> Target {
+        Target {
+            chat_id: chat_id.to_string(),
+            template: None,
+            currencies: None,
+            number_style: None,
+        }
+    }
+
+    /// Whether this target is the chat with numeric id `chat_id` and, for a
+    /// public chat, `username`; targets may be configured either way.
+    pub fn is_chat(&self, chat_id: &str, username: Option<&str>) -> bool {
+        if self.chat_id == chat_id {
+            return true;
+        }
+        match (self.chat_id.strip_prefix('@'), username) {
+            (Some(handle), Some(username)) => handle.eq_ignore_ascii_case(username),
+            _ => false,
+        }
+    }
+
+    /// `message` narrowed to this target's currencies and number style.
+    pub fn view(&self, message: &Message) -> Message {
+        let wanted = |code: &str| {
+            self.currencies
+                .as_ref()
+                .is_none_or(|codes| codes.iter().any(|c| c.eq_ignore_ascii_case(code)))
+        };
+        Message {
+            sections: message
+                .sections
+                .iter()
+                .map(|s| Section {
+                    title: s.title.clone(),
+                    lines: s
+                        .lines
+                        .iter()
+                        .filter(|l| wanted(l.currency.code()))
+                        .cloned()
+                        .collect(),
+                })
+                .collect(),
+            footer: message.footer.clone(),
+            number_style: self.number_style.unwrap_or(message.number_style),
+            posted_at: message.posted_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_target_matches_its_id() {
+        let target = Target::plain("-1001234567890");
+        assert!(target.is_chat("-1001234567890", None));
+        assert!(target.is_chat("-1001234567890", Some("my_channel")));
+        assert!(!target.is_chat("-1009999999999", None));
+    }
+
+    #[test]
+    fn handle_target_matches_the_chat_username() {
+        let target = Target::plain("@My_Channel");
+        assert!(target.is_chat("-1001234567890", Some("my_channel")));
+        assert!(!target.is_chat("-1001234567890", Some("other_channel")));
+        // کانال خصوصی username نداره
+        assert!(!target.is_chat("-1001234567890", None));
+    }
+}
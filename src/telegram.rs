@@ -0,0 +1,535 @@
+use std::time::Duration;
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::config::PostMode;
+use crate::error::BotError;
+use crate::retry::{MAX_ATTEMPTS, RETRY_BASE_DELAY, retry_with_backoff};
+
+/// Per-message `sendMessage` settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SendOptions {
+    pub parse_mode: Option<&'static str>,
+    /// Delivered without a notification sound (`disable_notification`).
+    pub silent: bool,
+    /// Can't be forwarded or saved (`protect_content`).
+    pub protect_content: bool,
+    /// Carries the inline 🔄 button that lets an admin ask for a fresh cycle.
+    pub refresh_button: bool,
+}
+
+impl SendOptions {
+    pub fn with_parse_mode(parse_mode: Option<&'static str>) -> SendOptions {
+        SendOptions {
+            parse_mode,
+            ..SendOptions::default()
+        }
+    }
+
+    /// The same options, but notifying subscribers even if posts are silent.
+    pub fn notifying(self) -> SendOptions {
+        SendOptions {
+            silent: false,
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SendMessageResponse {
+    pub ok: bool,
+    pub result: Option<TelegramMessage>,
+}
+
+#[derive(Deserialize)]
+pub struct TelegramMessage {
+    pub message_id: i64,
+}
+
+// بدنه‌ی پاسخ‌های ناموفق: {"ok":false,"error_code":429,"description":"...","parameters":{"retry_after":5}}
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    description: Option<String>,
+    parameters: Option<ResponseParameters>,
+}
+
+#[derive(Deserialize)]
+struct ResponseParameters {
+    retry_after: Option<u64>,
+}
+
+/// `callback_data` of the 🔄 button under channel posts.
+pub const REFRESH_CALLBACK: &str = "refresh";
+const REFRESH_MARKUP: &str =
+    r#"{"inline_keyboard":[[{"text":"🔄 بروزرسانی","callback_data":"refresh"}]]}"#;
+
+#[derive(Deserialize)]
+struct GetUpdatesResponse {
+    result: Vec<Update>,
+}
+
+#[derive(Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<IncomingMessage>,
+    pub inline_query: Option<InlineQuery>,
+    pub callback_query: Option<CallbackQuery>,
+}
+
+/// A press on an inline button.
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    pub id: String,
+    pub from: User,
+    // پیامی که دکمه زیرش بوده؛ برای پیام‌های خیلی قدیمی تلگرام نمی‌فرسته
+    pub message: Option<CallbackMessage>,
+    pub data: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct User {
+    pub id: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CallbackMessage {
+    pub message_id: i64,
+    pub chat: Chat,
+}
+
+/// `@MyBot 100usd` typed in any chat. Inline mode has to be switched on
+/// for the bot in BotFather (`/setinline`) before Telegram sends these.
+#[derive(Deserialize)]
+pub struct InlineQuery {
+    pub id: String,
+    pub query: String,
+}
+
+/// An `article` result for `answerInlineQuery`.
+#[derive(Serialize)]
+pub struct InlineQueryResult {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub input_message_content: InputTextMessageContent,
+}
+
+#[derive(Serialize)]
+pub struct InputTextMessageContent {
+    pub message_text: String,
+}
+
+impl InlineQueryResult {
+    pub fn article(id: &str, title: &str, text: &str) -> InlineQueryResult {
+        InlineQueryResult {
+            kind: "article",
+            id: id.to_string(),
+            title: title.to_string(),
+            description: text.to_string(),
+            input_message_content: InputTextMessageContent {
+                message_text: text.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct IncomingMessage {
+    pub chat: Chat,
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Chat {
+    pub id: i64,
+    // private / group / supergroup / channel
+    #[serde(rename = "type")]
+    pub kind: String,
+    // فقط برای کانال و گروه عمومی، بدون @
+    pub username: Option<String>,
+}
+
+// بدنه‌ی پاسخ فقط تا این طول لاگ می‌شه
+const LOGGED_BODY_CHARS: usize = 200;
+
+fn truncate_body(body: &str) -> &str {
+    match body.char_indices().nth(LOGGED_BODY_CHARS) {
+        Some((i, _)) => &body[..i],
+        None => body,
+    }
+}
+
+fn api_url(bot_token: &str, method: &str) -> String {
+    format!("https://api.telegram.org/bot{}/{}", bot_token, method)
+}
+
+async fn call(client: &Client, url: &str, params: &[(&str, &str)]) -> Result<String, BotError> {
+    execute(client.post(url).form(params)).await
+}
+
+async fn execute(request: RequestBuilder) -> Result<String, BotError> {
+    let resp = request.send().await.map_err(BotError::TelegramSend)?;
+
+    let status = resp.status();
+    // چون resp در اینجا move می‌شه، متن رو جدا می‌خونیم و فقط status قبلاً ذخیره شده
+    let body = resp.text().await.unwrap_or_default();
+    if status.is_success() {
+        debug!(
+            status = status.as_u16(),
+            body = truncate_body(&body),
+            "📨 telegram response"
+        );
+        return Ok(body);
+    }
+    warn!(
+        status = status.as_u16(),
+        body = truncate_body(&body),
+        "⚠️ telegram request failed"
+    );
+
+    let parsed: Option<ApiErrorResponse> = serde_json::from_str(&body).ok();
+    let retry_after = parsed
+        .as_ref()
+        .and_then(|p| p.parameters.as_ref())
+        .and_then(|p| p.retry_after);
+    if let (StatusCode::TOO_MANY_REQUESTS, Some(retry_after)) = (status, retry_after) {
+        return Err(BotError::TelegramRateLimited { retry_after });
+    }
+    let description = parsed.and_then(|p| p.description).unwrap_or(body);
+    Err(BotError::TelegramApi {
+        status,
+        description,
+    })
+}
+
+/// Sends a new message and returns its `message_id`.
+pub async fn send_telegram_message(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    text: &str,
+    options: SendOptions,
+) -> Result<i64, BotError> {
+    let url = api_url(bot_token, "sendMessage");
+    let mut params = vec![("chat_id", chat_id), ("text", text)];
+    if let Some(mode) = options.parse_mode {
+        params.push(("parse_mode", mode));
+    }
+    if options.silent {
+        params.push(("disable_notification", "true"));
+    }
+    if options.protect_content {
+        params.push(("protect_content", "true"));
+    }
+    if options.refresh_button {
+        params.push(("reply_markup", REFRESH_MARKUP));
+    }
+    let body = call(client, &url, &params).await?;
+    sent_message_id(body)
+}
+
+fn sent_message_id(body: String) -> Result<i64, BotError> {
+    let parsed: SendMessageResponse =
+        serde_json::from_str(&body).map_err(|source| BotError::TelegramDecode {
+            body: body.clone(),
+            source,
+        })?;
+    match parsed.result {
+        Some(msg) if parsed.ok => Ok(msg.message_id),
+        _ => Err(BotError::TelegramDecode {
+            body,
+            source: serde::de::Error::custom("missing result.message_id"),
+        }),
+    }
+}
+
+/// Uploads a PNG with `sendPhoto` and returns the new `message_id`. The
+/// body is built as multipart/form-data by hand, since the photo is sent
+/// as a file rather than a URL.
+pub async fn send_telegram_photo(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    png: &[u8],
+    caption: &str,
+) -> Result<i64, BotError> {
+    let url = api_url(bot_token, "sendPhoto");
+    let boundary = format!("peybot-{:x}", crate::storage::unix_now());
+
+    let mut body = Vec::with_capacity(png.len() + 512);
+    for (name, value) in [("chat_id", chat_id), ("caption", caption)] {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"photo\"; filename=\"chart.png\"\r\nContent-Type: image/png\r\n\r\n",
+            boundary
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(png);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let request = client
+        .post(&url)
+        .header(
+            CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body);
+    let body = execute(request).await?;
+    sent_message_id(body)
+}
+
+/// Replaces the text of an existing message. Telegram rejects edits that
+/// don't change anything; that's treated as success.
+pub async fn edit_telegram_message(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    message_id: i64,
+    text: &str,
+    options: SendOptions,
+) -> Result<(), BotError> {
+    let url = api_url(bot_token, "editMessageText");
+    let message_id = message_id.to_string();
+    let mut params = vec![
+        ("chat_id", chat_id),
+        ("message_id", message_id.as_str()),
+        ("text", text),
+    ];
+    if let Some(mode) = options.parse_mode {
+        params.push(("parse_mode", mode));
+    }
+    // بدون reply_markup ویرایش دکمه رو پاک می‌کنه
+    if options.refresh_button {
+        params.push(("reply_markup", REFRESH_MARKUP));
+    }
+    match call(client, &url, &params).await {
+        Ok(_) => Ok(()),
+        Err(BotError::TelegramApi { description, .. })
+            if description.contains("message is not modified") =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn pin_telegram_message(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    message_id: i64,
+) -> Result<(), BotError> {
+    let url = api_url(bot_token, "pinChatMessage");
+    let message_id = message_id.to_string();
+    let params = [
+        ("chat_id", chat_id),
+        ("message_id", message_id.as_str()),
+        ("disable_notification", "true"),
+    ];
+    call(client, &url, &params).await.map(|_| ())
+}
+
+#[derive(Serialize)]
+struct BotCommand<'a> {
+    command: &'a str,
+    description: &'a str,
+}
+
+const BOT_DESCRIPTION: &str = "نرخ لحظه‌ای دلار، یورو، لیر، طلا و سکه به تومان. \
+/rate برای همه‌ی نرخ‌ها و /convert برای تبدیل.";
+
+/// Registers the command menu and the bot description. Failures are only
+/// logged; the bot works without them.
+pub async fn setup_bot_metadata(client: &Client, bot_token: &str, commands: &[(&str, &str)]) {
+    let commands: Vec<BotCommand> = commands
+        .iter()
+        .map(|(command, description)| BotCommand {
+            command,
+            description,
+        })
+        .collect();
+    let request = client
+        .post(api_url(bot_token, "setMyCommands"))
+        .json(&serde_json::json!({ "commands": commands }));
+    match execute(request).await {
+        Ok(_) => info!(count = commands.len(), "📋 منوی دستورها ثبت شد"),
+        Err(e) => warn!(error = %e, "⚠️ ثبت منوی دستورها ناموفق"),
+    }
+
+    let url = api_url(bot_token, "setMyDescription");
+    if let Err(e) = call(client, &url, &[("description", BOT_DESCRIPTION)]).await {
+        warn!(error = %e, "⚠️ ثبت توضیحات ربات ناموفق");
+    }
+}
+
+/// Answers an inline query; an empty `results` just shows nothing.
+pub async fn answer_inline_query(
+    client: &Client,
+    bot_token: &str,
+    inline_query_id: &str,
+    results: &[InlineQueryResult],
+    cache_time: Duration,
+) -> Result<(), BotError> {
+    let url = api_url(bot_token, "answerInlineQuery");
+    let results = serde_json::to_string(results).map_err(|source| BotError::TelegramDecode {
+        body: String::new(),
+        source,
+    })?;
+    let cache_time = cache_time.as_secs().to_string();
+    let params = [
+        ("inline_query_id", inline_query_id),
+        ("results", results.as_str()),
+        ("cache_time", cache_time.as_str()),
+    ];
+    call(client, &url, &params).await.map(|_| ())
+}
+
+/// Answers a button press; `text` shows as a short toast to the presser.
+pub async fn answer_callback_query(
+    client: &Client,
+    bot_token: &str,
+    callback_query_id: &str,
+    text: &str,
+) -> Result<(), BotError> {
+    let url = api_url(bot_token, "answerCallbackQuery");
+    let params = [("callback_query_id", callback_query_id), ("text", text)];
+    call(client, &url, &params).await.map(|_| ())
+}
+
+/// Long-polls `getUpdates`, waiting up to `poll_timeout` for new messages.
+pub async fn get_updates(
+    client: &Client,
+    bot_token: &str,
+    offset: i64,
+    poll_timeout: Duration,
+) -> Result<Vec<Update>, BotError> {
+    let url = api_url(bot_token, "getUpdates");
+    let offset = offset.to_string();
+    let poll_secs = poll_timeout.as_secs().to_string();
+    let params = [
+        ("offset", offset.as_str()),
+        ("timeout", poll_secs.as_str()),
+        (
+            "allowed_updates",
+            r#"["message","inline_query","callback_query"]"#,
+        ),
+    ];
+    // تایم‌اوت کلاینت از long-poll کوتاه‌تره، پس برای این درخواست جدا تنظیمش می‌کنیم
+    let request = client
+        .post(&url)
+        .form(&params)
+        .timeout(poll_timeout + Duration::from_secs(10));
+    let body = execute(request).await?;
+
+    serde_json::from_str::<GetUpdatesResponse>(&body)
+        .map(|r| r.result)
+        .map_err(|source| BotError::TelegramDecode { body, source })
+}
+
+pub fn log_send_failure(chat_id: &str, e: &BotError) {
+    match e.root() {
+        BotError::TelegramApi { description, .. } if description.contains("chat not found") => {
+            error!(chat_id = %chat_id, "🚫 چت پیدا نشد — CHANNEL_IDS رو بررسی کنید")
+        }
+        BotError::TelegramApi { description, .. }
+            if description.contains("bot was blocked")
+                || description.contains("bot was kicked")
+                || description.contains("not a member") =>
+        {
+            error!(chat_id = %chat_id, error = %e, "🚫 ربات مسدود شده یا عضو نیست")
+        }
+        BotError::TelegramRateLimited { .. } => {
+            warn!(chat_id = %chat_id, error = %e, "⏳ محدودیت نرخ تلگرام برطرف نشد")
+        }
+        BotError::TelegramApi { .. } => {
+            warn!(chat_id = %chat_id, error = %e, "⚠️ تلگرام پاسخ غیرموفق داد")
+        }
+        _ => error!(chat_id = %chat_id, error = %e, "❌ خطا در ارسال به تلگرام"),
+    }
+}
+
+// پیام جدید می‌فرسته و پینش می‌کنه؛ شناسه‌اش برای ویرایش‌های بعدی برمی‌گرده
+async fn send_and_pin(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    text: &str,
+    options: SendOptions,
+) -> Result<i64, BotError> {
+    let message_id = retry_with_backoff(
+        || send_telegram_message(client, bot_token, chat_id, text, options),
+        MAX_ATTEMPTS,
+        RETRY_BASE_DELAY,
+    )
+    .await?;
+
+    let pinned = retry_with_backoff(
+        || pin_telegram_message(client, bot_token, chat_id, message_id),
+        MAX_ATTEMPTS,
+        RETRY_BASE_DELAY,
+    )
+    .await;
+    if let Err(e) = pinned {
+        warn!(chat_id = %chat_id, error = %e, "⚠️ پین کردن پیام ناموفق");
+    }
+    Ok(message_id)
+}
+
+/// Posts `text` to `chat_id`. In [`PostMode::Edit`] the post `message_id`
+/// is edited, or a new one sent and pinned when there is none yet; its id
+/// is returned for the caller to store, so no state lock is held while
+/// Telegram is retried.
+pub async fn publish(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    text: &str,
+    options: SendOptions,
+    mode: PostMode,
+    message_id: Option<i64>,
+) -> Result<Option<i64>, BotError> {
+    if mode == PostMode::Send {
+        return retry_with_backoff(
+            || send_telegram_message(client, bot_token, chat_id, text, options),
+            MAX_ATTEMPTS,
+            RETRY_BASE_DELAY,
+        )
+        .await
+        .map(|_| None);
+    }
+
+    let Some(message_id) = message_id else {
+        return send_and_pin(client, bot_token, chat_id, text, options)
+            .await
+            .map(Some);
+    };
+    let edited = retry_with_backoff(
+        || edit_telegram_message(client, bot_token, chat_id, message_id, text, options),
+        MAX_ATTEMPTS,
+        RETRY_BASE_DELAY,
+    )
+    .await;
+    match edited {
+        Err(e) if e.is_message_not_found() => {
+            info!(chat_id = %chat_id, "ℹ️ پیام قبلی پیدا نشد، پیام جدید ارسال می‌شه");
+            send_and_pin(client, bot_token, chat_id, text, options)
+                .await
+                .map(Some)
+        }
+        other => other.map(|_| Some(message_id)),
+    }
+}
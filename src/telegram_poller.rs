@@ -0,0 +1,591 @@
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use reqwest::Client;
+use tokio::sync::{RwLock, mpsc};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::chart::{ChartWindow, render_line_chart};
+use crate::message::{RateLine, Unit, fmt_int};
+use crate::state::SharedState;
+use crate::storage::{Storage, unix_now};
+use crate::telegram::{
+    CallbackQuery, Chat, InlineQuery, InlineQueryResult, REFRESH_CALLBACK, SendOptions,
+    answer_callback_query, answer_inline_query, get_updates, send_telegram_message,
+    send_telegram_photo,
+};
+
+const POLL_TIMEOUT: Duration = Duration::from_secs(30);
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+// نرخ‌ها هر چرخه عوض می‌شن، پس تلگرام نباید جواب inline رو زیاد نگه داره
+const INLINE_CACHE_TIME: Duration = Duration::from_secs(30);
+
+// "100usd"، "2.5 eur"
+static INLINE_QUERY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+(\.\d+)?)\s*([a-zA-Z]{3})").expect("valid regex"));
+
+// کمتر از این تعداد نقطه نمودار نمی‌شه
+const MIN_CHART_POINTS: usize = 2;
+
+// ارزهایی که جواب inline به اون‌ها تبدیل می‌کنه، به جز خود تومان
+const INLINE_TARGETS: &[&str] = &["EUR", "TRY"];
+
+/// Latest values computed by the posting loop, shared with the command poller.
+#[derive(Clone, Default)]
+pub struct RateSnapshot {
+    pub lines: Vec<RateLine>,
+    /// When the cycle that produced `lines` ran; `None` before the first one.
+    pub taken_at: Option<DateTime<Utc>>,
+}
+
+pub type SharedSnapshot = Arc<RwLock<RateSnapshot>>;
+
+/// An admin pressed 🔄 under this message: run a cycle now and edit it.
+#[derive(Debug, Clone)]
+pub struct RefreshRequest {
+    // شناسه‌ی عددی، حتی وقتی کانال در تنظیمات با @ آمده
+    pub chat_id: String,
+    pub username: Option<String>,
+    pub message_id: i64,
+}
+
+/// Who may press 🔄, and where their presses go.
+pub struct RefreshButton {
+    pub admin_ids: Vec<i64>,
+    pub requests: mpsc::Sender<RefreshRequest>,
+}
+
+fn entry_line(line: &RateLine) -> String {
+    format!(
+        "{} {}: {} {}\n",
+        line.emoji,
+        line.name,
+        line.unit.amount(line.value),
+        line.unit.label()
+    )
+}
+
+// پاسخ /rate یا /rate usd
+fn rate_reply(snapshot: &RateSnapshot, code: Option<&str>) -> String {
+    if snapshot.lines.is_empty() {
+        return "هنوز نرخی دریافت نشده".to_string();
+    }
+    match code {
+        None => snapshot.lines.iter().map(entry_line).collect(),
+        Some(code) => match snapshot
+            .lines
+            .iter()
+            .find(|e| e.currency.code().eq_ignore_ascii_case(code))
+        {
+            Some(e) => entry_line(e),
+            None => {
+                let codes: Vec<&str> = snapshot.lines.iter().map(|e| e.currency.code()).collect();
+                format!("ارز {} پیدا نشد. ارزهای موجود: {}", code, codes.join(", "))
+            }
+        },
+    }
+}
+
+// ارقام فارسی/عربی → لاتین، و جداکننده‌ی اعشار فارسی → نقطه
+fn normalize_digits(s: &str) -> String {
+    s.chars()
+        .filter(|c| !matches!(c, ',' | '٬' | '_'))
+        .map(|c| match c {
+            '۰'..='۹' => char::from_u32(c as u32 - '۰' as u32 + '0' as u32).unwrap_or(c),
+            '٠'..='٩' => char::from_u32(c as u32 - '٠' as u32 + '0' as u32).unwrap_or(c),
+            '٫' => '.',
+            _ => c,
+        })
+        .collect()
+}
+
+fn parse_amount(raw: &str) -> Option<f64> {
+    let amount: f64 = normalize_digits(raw).parse().ok()?;
+    (amount.is_finite() && amount >= 0.0).then_some(amount)
+}
+
+/// Commands shown in Telegram's menu, without the leading slash.
+pub const BOT_COMMANDS: &[(&str, &str)] = &[
+    ("rate", "نرخ همه‌ی ارزها یا یک ارز: /rate USD"),
+    ("convert", "تبدیل مبلغ: /convert 100 USD EUR"),
+    ("chart", "نمودار قیمت: /chart USD 7d"),
+    (
+        "subscribe",
+        "دریافت هر پست در همین چت (دوباره بزنید برای لغو)",
+    ),
+    ("help", "راهنما"),
+];
+
+const HELP_TEXT: &str = "دستورها:
+/rate — همه‌ی نرخ‌ها
+/rate USD — نرخ یک ارز
+/convert 100 USD — تبدیل به تومان
+/convert 100 USD EUR — تبدیل بین دو ارز
+/chart USD 7d — نمودار قیمت (1d، 7d یا 30d)
+/subscribe — دریافت هر پست در همین چت؛ دوباره برای لغو
+/help — همین راهنما";
+
+fn is_toman(code: &str) -> bool {
+    matches!(code.to_ascii_uppercase().as_str(), "IRT" | "TOMAN") || code == "تومان"
+}
+
+// ارزش یک واحد از ارز به تومان؛ برای خود تومان ۱
+fn toman_per_unit(snapshot: &RateSnapshot, code: &str) -> Result<f64, String> {
+    if is_toman(code) {
+        return Ok(1.0);
+    }
+    let Some(line) = snapshot
+        .lines
+        .iter()
+        .find(|l| l.currency.code().eq_ignore_ascii_case(code))
+    else {
+        let codes: Vec<&str> = snapshot.lines.iter().map(|l| l.currency.code()).collect();
+        return Err(format!(
+            "ارز {} پشتیبانی نمی‌شه. ارزهای موجود: {}",
+            code,
+            codes.join(", ")
+        ));
+    };
+    // تبدیل فقط برای نرخ‌های تومانی معنی داره، نه مثلاً انس به دلار
+    if line.unit.base() != Unit::Toman || line.value <= 0 {
+        return Err(format!("{} {} قابل تبدیل نیست", line.emoji, line.name));
+    }
+    Ok(match line.unit {
+        Unit::TomanDecimals(places) => line.value as f64 / 10f64.powi(places as i32),
+        _ => line.value as f64,
+    })
+}
+
+// 1234.5 → "1,234.50"
+fn fmt_decimal(v: f64) -> String {
+    let cents = (v * 100.0).round() as i64;
+    format!("{}.{:02}", fmt_int(cents / 100), cents % 100)
+}
+
+// پاسخ /convert 500 TRY یا /convert 100 USD EUR
+fn convert_reply(snapshot: &RateSnapshot, args: &[&str]) -> String {
+    let (amount_raw, from, to) = match args {
+        [amount, from] => (*amount, *from, "IRT"),
+        [amount, from, to] => (*amount, *from, *to),
+        _ => return "استفاده: /convert 100 USD EUR".to_string(),
+    };
+    let Some(amount) = parse_amount(amount_raw) else {
+        return format!("مبلغ {} معتبر نیست", amount_raw);
+    };
+    if snapshot.lines.is_empty() {
+        return "هنوز نرخی دریافت نشده".to_string();
+    }
+    let from_toman = match toman_per_unit(snapshot, from) {
+        Ok(v) => v,
+        Err(reply) => return reply,
+    };
+    let to_toman = match toman_per_unit(snapshot, to) {
+        Ok(v) => v,
+        Err(reply) => return reply,
+    };
+
+    let toman = amount * from_toman;
+    let result = toman / to_toman;
+    // i64::MAX as f64 گرد می‌شه به 2^63 که خودش جا نمی‌شه، پس >= مقایسه می‌کنیم
+    if !result.is_finite() || toman >= i64::MAX as f64 || result * 100.0 >= i64::MAX as f64 {
+        return "مبلغ خیلی بزرگ است".to_string();
+    }
+    if is_toman(to) {
+        format!(
+            "{} {} = {} تومان",
+            amount_raw,
+            from.to_uppercase(),
+            fmt_int(result.round() as i64)
+        )
+    } else {
+        format!(
+            "{} {} = {} {}",
+            amount_raw,
+            from.to_uppercase(),
+            fmt_decimal(result),
+            to.to_uppercase()
+        )
+    }
+}
+
+// "@MyBot 100usd" → عنوان و متن جواب؛ None اگه قابل فهم نباشه
+fn inline_reply(snapshot: &RateSnapshot, query: &str) -> Option<(String, String)> {
+    let query = normalize_digits(query);
+    let caps = INLINE_QUERY_RE.captures(&query)?;
+    let amount: f64 = caps[1].parse().ok()?;
+    let from = caps[3].to_uppercase();
+    let from_toman = toman_per_unit(snapshot, &from).ok()?;
+
+    let toman = amount * from_toman;
+    if !toman.is_finite() || toman >= i64::MAX as f64 {
+        return None;
+    }
+    let title = format!(
+        "{} {} = {} تومان",
+        &caps[1],
+        from,
+        fmt_int(toman.round() as i64)
+    );
+    let mut text = format!("{}\n", title);
+    for to in INLINE_TARGETS.iter().filter(|to| **to != from) {
+        if let Ok(to_toman) = toman_per_unit(snapshot, to) {
+            text.push_str(&format!(
+                "{} {} = {} {}\n",
+                &caps[1],
+                from,
+                fmt_decimal(toman / to_toman),
+                to
+            ));
+        }
+    }
+    Some((title, text.trim_end().to_string()))
+}
+
+async fn handle_inline_query(
+    client: &Client,
+    bot_token: &str,
+    snapshot: &SharedSnapshot,
+    query: &InlineQuery,
+) {
+    let results: Vec<InlineQueryResult> = inline_reply(&*snapshot.read().await, &query.query)
+        .map(|(title, text)| InlineQueryResult::article("convert", &title, &text))
+        .into_iter()
+        .collect();
+    if let Err(e) =
+        answer_inline_query(client, bot_token, &query.id, &results, INLINE_CACHE_TIME).await
+    {
+        warn!(error = %e, "⚠️ پاسخ به inline query ناموفق");
+    }
+}
+
+async fn handle_callback(
+    client: &Client,
+    bot_token: &str,
+    refresh: &RefreshButton,
+    query: &CallbackQuery,
+) {
+    // جواب دادن لازمه حتی بی‌متن، وگرنه دکمه برای کاربر در حال بارگذاری می‌مونه
+    let toast = match (query.data.as_deref(), &query.message) {
+        (Some(REFRESH_CALLBACK), _) if !refresh.admin_ids.contains(&query.from.id) => {
+            "این دکمه فقط برای ادمین‌های کانال است 🙏"
+        }
+        (Some(REFRESH_CALLBACK), Some(message)) => {
+            let request = RefreshRequest {
+                chat_id: message.chat.id.to_string(),
+                username: message.chat.username.clone(),
+                message_id: message.message_id,
+            };
+            match refresh.requests.try_send(request) {
+                Ok(()) => {
+                    info!(
+                        user_id = query.from.id,
+                        chat_id = message.chat.id,
+                        "🔄 refresh requested"
+                    );
+                    "🔄 در حال به‌روزرسانی…"
+                }
+                // یکی در صف هست؛ همون پیام رو به‌روز می‌کنه
+                Err(_) => "به‌روزرسانی در جریان است",
+            }
+        }
+        (Some(REFRESH_CALLBACK), None) => "این پیام دیگر قابل به‌روزرسانی نیست",
+        _ => "",
+    };
+    if let Err(e) = answer_callback_query(client, bot_token, &query.id, toast).await {
+        warn!(error = %e, "⚠️ پاسخ به دکمه ناموفق");
+    }
+}
+
+// پاسخ /chart usd 7d: عکس نمودار و کپشنش، یا متن خطا
+async fn chart_reply(
+    db: &Storage,
+    snapshot: &SharedSnapshot,
+    args: &[&str],
+) -> Result<(Vec<u8>, String), String> {
+    const USAGE: &str = "مثال: /chart USD 7d (بازه: 1d، 7d یا 30d)";
+    let (code, window) = match args {
+        [code, window] => (*code, *window),
+        _ => return Err(USAGE.to_string()),
+    };
+    let Some(window) = ChartWindow::parse(window) else {
+        return Err(format!("بازه‌ی {} نامعتبره. {}", window, USAGE));
+    };
+    let line = {
+        let snapshot = snapshot.read().await;
+        if snapshot.lines.is_empty() {
+            return Err("هنوز نرخی دریافت نشده".to_string());
+        }
+        // فقط نرخ‌های تومانی تاریخچه دارن
+        let toman = || {
+            snapshot
+                .lines
+                .iter()
+                .filter(|l| l.unit.base() == Unit::Toman)
+        };
+        match toman().find(|l| l.currency.code().eq_ignore_ascii_case(code)) {
+            Some(line) => line.clone(),
+            None => {
+                let codes: Vec<&str> = toman().map(|l| l.currency.code()).collect();
+                return Err(format!(
+                    "برای {} نمودار نداریم. ارزهای موجود: {}",
+                    code,
+                    codes.join(", ")
+                ));
+            }
+        }
+    };
+
+    let records = db
+        .get_rates_since(line.currency.code(), unix_now() - window.secs())
+        .await
+        .map_err(|e| {
+            warn!(currency = %line.currency, error = %e, "⚠️ خواندن تاریخچه برای نمودار ناموفق");
+            "خواندن تاریخچه ناموفق بود، کمی بعد دوباره امتحان کنید".to_string()
+        })?;
+    if records.len() < MIN_CHART_POINTS {
+        return Err(format!(
+            "هنوز تاریخچه‌ی کافی از {} در {} گذشته نداریم",
+            line.name,
+            window.label()
+        ));
+    }
+
+    let points: Vec<(i64, i64)> = records
+        .iter()
+        .map(|r| (r.fetched_at, r.value_toman))
+        .collect();
+    let high = points.iter().map(|p| p.1).max().unwrap_or(0);
+    let low = points.iter().map(|p| p.1).min().unwrap_or(0);
+    let last = points.last().map_or(0, |p| p.1);
+    let caption = format!(
+        "{} {} — {} گذشته\nبیشترین: {} | کمترین: {}\nآخرین: {} تومان",
+        line.emoji,
+        line.name,
+        window.label(),
+        fmt_int(high),
+        fmt_int(low),
+        fmt_int(last)
+    );
+    Ok((render_line_chart(&points), caption))
+}
+
+async fn handle_chart(
+    client: &Client,
+    bot_token: &str,
+    chat_id: &str,
+    db: &Storage,
+    snapshot: &SharedSnapshot,
+    args: &[&str],
+) {
+    let sent = match chart_reply(db, snapshot, args).await {
+        Ok((png, caption)) => send_telegram_photo(client, bot_token, chat_id, &png, &caption).await,
+        Err(reply) => {
+            send_telegram_message(client, bot_token, chat_id, &reply, SendOptions::default()).await
+        }
+    };
+    if let Err(e) = sent {
+        warn!(chat_id, error = %e, "⚠️ ارسال نمودار ناموفق");
+    }
+}
+
+/// Adds `chat_id` to `subscribers`, or removes it if it's already there.
+/// Returns whether the chat is subscribed afterwards.
+pub fn toggle_subscriber(subscribers: &mut Vec<i64>, chat_id: i64) -> bool {
+    if let Some(i) = subscribers.iter().position(|&id| id == chat_id) {
+        subscribers.remove(i);
+        false
+    } else {
+        subscribers.push(chat_id);
+        true
+    }
+}
+
+async fn handle_subscribe(store: &SharedState, chat_id: i64) -> String {
+    let mut store = store.lock().await;
+    let subscribed = toggle_subscriber(&mut store.state.subscribers, chat_id);
+    if let Err(e) = store.save() {
+        warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+    }
+    info!(chat_id, subscribed, "📬 subscription changed");
+    if subscribed {
+        "✅ از این به بعد هر پست نرخ‌ها اینجا هم فرستاده می‌شه. برای لغو دوباره /subscribe بزنید."
+            .to_string()
+    } else {
+        "🔕 اشتراک لغو شد.".to_string()
+    }
+}
+
+// گروه‌ها و چت خصوصی جواب می‌گیرن؛ فقط خود کانال‌ها نه
+fn answers_chat(chat: &Chat) -> bool {
+    chat.kind != "channel"
+}
+
+// "/rate@MyBot usd" → ("/rate", ["usd"])
+fn parse_command(text: &str) -> Option<(&str, Vec<&str>)> {
+    let mut parts = text.split_whitespace();
+    let head = parts.next()?;
+    if !head.starts_with('/') {
+        return None;
+    }
+    let command = head.split('@').next().unwrap_or(head);
+    Some((command, parts.collect()))
+}
+
+async fn handle_text(snapshot: &SharedSnapshot, text: &str) -> Option<String> {
+    let (command, args) = parse_command(text)?;
+    match command {
+        "/rate" => Some(rate_reply(&*snapshot.read().await, args.first().copied())),
+        "/convert" => Some(convert_reply(&*snapshot.read().await, &args)),
+        "/help" | "/start" => Some(HELP_TEXT.to_string()),
+        _ => None,
+    }
+}
+
+/// Answers bot commands sent in private chats and groups, inline queries
+/// from any chat and 🔄 presses under posts, until the process exits.
+/// Messages from channels are skipped.
+pub async fn run_poller(
+    client: Client,
+    bot_token: String,
+    snapshot: SharedSnapshot,
+    store: SharedState,
+    db: Arc<Storage>,
+    refresh: RefreshButton,
+) {
+    let mut offset = store
+        .lock()
+        .await
+        .state
+        .last_update_id
+        .map_or(0, |id| id + 1);
+    loop {
+        let updates = match get_updates(&client, &bot_token, offset, POLL_TIMEOUT).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                warn!(error = %e, "⚠️ دریافت پیام‌های ربات ناموفق");
+                sleep(ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+
+        if let Some(last) = updates.iter().map(|u| u.update_id).max() {
+            let mut store = store.lock().await;
+            store.state.last_update_id = Some(last);
+            if let Err(e) = store.save() {
+                warn!(error = %e, "⚠️ ذخیره‌ی فایل وضعیت ناموفق");
+            }
+        }
+
+        for update in updates {
+            offset = offset.max(update.update_id + 1);
+            if let Some(query) = &update.inline_query {
+                handle_inline_query(&client, &bot_token, &snapshot, query).await;
+                continue;
+            }
+            if let Some(query) = &update.callback_query {
+                handle_callback(&client, &bot_token, &refresh, query).await;
+                continue;
+            }
+            let Some(message) = update.message else {
+                continue;
+            };
+            if !answers_chat(&message.chat) {
+                continue;
+            }
+            let Some(text) = message.text.as_deref() else {
+                continue;
+            };
+            if let Some(("/chart", args)) = parse_command(text) {
+                let chat_id = message.chat.id.to_string();
+                handle_chart(&client, &bot_token, &chat_id, &db, &snapshot, &args).await;
+                continue;
+            }
+            let reply = match parse_command(text) {
+                Some(("/subscribe", _)) => Some(handle_subscribe(&store, message.chat.id).await),
+                _ => handle_text(&snapshot, text).await,
+            };
+            let Some(reply) = reply else {
+                continue;
+            };
+
+            let chat_id = message.chat.id.to_string();
+            if let Err(e) = send_telegram_message(
+                &client,
+                &bot_token,
+                &chat_id,
+                &reply,
+                SendOptions::default(),
+            )
+            .await
+            {
+                warn!(chat_id, error = %e, "⚠️ پاسخ به پیام ناموفق");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_registered_command_is_in_the_help() {
+        for (command, _) in BOT_COMMANDS {
+            assert!(
+                HELP_TEXT.contains(&format!("/{} ", command)),
+                "/{} missing from HELP_TEXT",
+                command
+            );
+        }
+        assert!(BOT_COMMANDS.iter().any(|(c, _)| *c == "subscribe"));
+    }
+
+    fn chat(kind: &str) -> Chat {
+        Chat {
+            id: -1001234567890,
+            kind: kind.to_string(),
+            username: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn group_rate_command_is_answered() {
+        let line = RateLine::new(crate::currency::Currency::Usd, "💵", "دلار", 102_540, None);
+        let snapshot: SharedSnapshot = Arc::new(RwLock::new(RateSnapshot {
+            lines: vec![line],
+            taken_at: None,
+        }));
+        for kind in ["group", "supergroup", "private"] {
+            assert!(answers_chat(&chat(kind)), "{kind}");
+        }
+        let reply = handle_text(&snapshot, "/rate@PeyBot usd").await.unwrap();
+        assert!(reply.contains("102,540"), "{reply}");
+    }
+
+    #[test]
+    fn channel_posts_are_ignored() {
+        assert!(!answers_chat(&chat("channel")));
+    }
+
+    #[test]
+    fn subscribe_toggles() {
+        let mut subscribers = vec![111];
+        assert!(toggle_subscriber(&mut subscribers, 222));
+        assert_eq!(subscribers, [111, 222]);
+        assert!(!toggle_subscriber(&mut subscribers, 111));
+        assert_eq!(subscribers, [222]);
+    }
+
+    #[test]
+    fn command_with_bot_name_and_args() {
+        assert_eq!(
+            parse_command("/subscribe@PeyBot"),
+            Some(("/subscribe", vec![]))
+        );
+        assert_eq!(parse_command("/rate usd"), Some(("/rate", vec!["usd"])));
+        assert_eq!(parse_command("سلام"), None);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+
+use regex::{Captures, Regex};
+use tracing::warn;
+
+use crate::message::{Message, ParseMode, RateLine, fmt_delta};
+
+// {USD}، {LIRA}، {TIMESTAMP}
+static PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([A-Za-z0-9_]+)\}").expect("valid regex"));
+
+const LINES_START: &str = "{#lines}";
+const LINES_END: &str = "{/lines}";
+
+// متن ثابت با placeholder، یا بدنه‌ی {#lines} که برای هر خط پست تکرار می‌شه
+enum Part {
+    Text(String),
+    Lines(String),
+}
+
+/// A post layout from `MESSAGE_TEMPLATE`. `{CODE}` is the value of any
+/// line in the post (`{USD}`, `{GOLD18}`, …), `{LIRA}` is `{TRY}`, and
+/// `{TIMESTAMP}` / `{FOOTER}` are the Tehran time and the usual footer, and
+/// `{JALALI}` is the `🗓` date line of the built-in layout.
+/// Between `{#lines}` and `{/lines}`, the text is repeated for each line of
+/// the post with `{EMOJI}`, `{NAME}`, `{CODE}`, `{VALUE}`, `{UNIT}` and
+/// `{DELTA}` of that line.
+/// Values are escaped for the parse mode; the template text itself is not.
+/// Without a template the built-in layout is used.
+pub struct MessageTemplate {
+    parts: Vec<Part>,
+    // هر placeholder ناشناخته فقط یک بار هشدار می‌ده، نه هر چرخه
+    warned: Mutex<HashSet<String>>,
+}
+
+impl MessageTemplate {
+    /// Splits `source` into text and `{#lines}` blocks. Fails on a block
+    /// that isn't closed, a stray `{/lines}` or nested blocks, so a broken
+    /// template is caught at startup rather than when posting.
+    pub fn parse(source: &str) -> Result<MessageTemplate, String> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find(LINES_START) {
+            let (text, block) = rest.split_at(start);
+            if text.contains(LINES_END) {
+                return Err(format!("{} without {}", LINES_END, LINES_START));
+            }
+            parts.push(Part::Text(text.to_string()));
+            let block = &block[LINES_START.len()..];
+            let Some(end) = block.find(LINES_END) else {
+                return Err(format!(
+                    "{} is never closed with {}",
+                    LINES_START, LINES_END
+                ));
+            };
+            let body = &block[..end];
+            if body.contains(LINES_START) {
+                return Err(format!("{} blocks can't be nested", LINES_START));
+            }
+            parts.push(Part::Lines(body.to_string()));
+            rest = &block[end + LINES_END.len()..];
+        }
+        if rest.contains(LINES_END) {
+            return Err(format!("{} without {}", LINES_END, LINES_START));
+        }
+        parts.push(Part::Text(rest.to_string()));
+        Ok(MessageTemplate {
+            parts,
+            warned: Mutex::new(HashSet::new()),
+        })
+    }
+
+    // مقدارهای یک خط برای بدنه‌ی {#lines}
+    fn line_values(mode: ParseMode, message: &Message, line: &RateLine) -> HashMap<String, String> {
+        let style = message.number_style;
+        let delta = fmt_delta(line.value, line.previous_value, line.unit);
+        [
+            ("EMOJI", line.emoji.clone()),
+            ("NAME", line.name.clone()),
+            ("CODE", line.currency.to_string()),
+            ("VALUE", style.apply(&line.unit.amount(line.value))),
+            ("UNIT", line.unit.label().to_string()),
+            ("DELTA", style.apply(&delta)),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), mode.text(&v)))
+        .collect()
+    }
+
+    fn values(mode: ParseMode, message: &Message) -> HashMap<String, String> {
+        let style = message.number_style;
+        let mut values: HashMap<String, String> = message
+            .lines()
+            .map(|l| {
+                let amount = style.apply(&l.unit.amount(l.value));
+                (l.currency.to_string(), mode.text(&amount))
+            })
+            .collect();
+        if let Some(lira) = values.get("TRY").cloned() {
+            values.insert("LIRA".to_string(), lira);
+        }
+        let timestamp = style.apply(&message.posted_at.format("%Y-%m-%d %H:%M").to_string());
+        values.insert("TIMESTAMP".to_string(), mode.text(&timestamp));
+        values.insert("JALALI".to_string(), mode.text(&message.date_line()));
+        values.insert("FOOTER".to_string(), mode.text(&message.footer));
+        values
+    }
+
+    /// Fills in the placeholders. Unknown ones, including currencies missing
+    /// from this post, are left as written.
+    pub fn render(&self, mode: ParseMode, message: &Message) -> String {
+        let values = MessageTemplate::values(mode, message);
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(&self.fill(text, &values, None)),
+                Part::Lines(body) => {
+                    for line in message.lines() {
+                        let line_values = MessageTemplate::line_values(mode, message, line);
+                        out.push_str(&self.fill(body, &values, Some(&line_values)));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    // مقدارهای خط (اگه باشه) جلوتر از مقدارهای کل پست‌ان
+    fn fill(
+        &self,
+        text: &str,
+        values: &HashMap<String, String>,
+        line_values: Option<&HashMap<String, String>>,
+    ) -> String {
+        PLACEHOLDER_RE
+            .replace_all(text, |caps: &Captures| {
+                let name = caps[1].to_uppercase();
+                match line_values
+                    .and_then(|l| l.get(&name))
+                    .or_else(|| values.get(&name))
+                {
+                    Some(value) => value.clone(),
+                    None => {
+                        self.warn_unknown(&caps[1]);
+                        caps[0].to_string()
+                    }
+                }
+            })
+            .into_owned()
+    }
+
+    fn warn_unknown(&self, name: &str) {
+        if let Ok(mut warned) = self.warned.lock()
+            && warned.insert(name.to_string())
+        {
+            warn!(placeholder = %name, "⚠️ placeholder ناشناخته در MESSAGE_TEMPLATE");
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::currency::Currency;
+use crate::message::Section;
+
+/// Default for `SMA_N`.
+pub const DEFAULT_SMA_WINDOW: usize = 10;
+
+// تا ۰.۱٪ فاصله از میانگین «بدون روند» حساب می‌شه
+const FLAT_PCT: f64 = 0.1;
+
+/// Mean of the readings in `history`, or `None` when it is empty.
+pub fn compute_sma(history: &VecDeque<i64>) -> Option<f64> {
+    if history.is_empty() {
+        return None;
+    }
+    Some(history.iter().sum::<i64>() as f64 / history.len() as f64)
+}
+
+/// 📈 above the average, 📉 below it, ➡️ within 0.1% of it.
+pub fn trend_marker(current: i64, sma: f64) -> &'static str {
+    let diff = current as f64 - sma;
+    if sma == 0.0 || (diff / sma * 100.0).abs() <= FLAT_PCT {
+        "➡️"
+    } else if diff > 0.0 {
+        "📈"
+    } else {
+        "📉"
+    }
+}
+
+/// The last `window` fresh values of each currency, for the trend marker.
+pub struct TrendTracker {
+    window: usize,
+    history: HashMap<Currency, VecDeque<i64>>,
+}
+
+impl TrendTracker {
+    pub fn new(window: usize) -> TrendTracker {
+        TrendTracker {
+            window,
+            history: HashMap::new(),
+        }
+    }
+
+    /// Marks each fresh line against the average of its previous `window`
+    /// values, then adds the line to the history. Until a currency has
+    /// `window` readings it gets no marker.
+    pub fn apply(&mut self, sections: &mut [Section]) {
+        let lines = sections
+            .iter_mut()
+            .flat_map(|s| s.lines.iter_mut())
+            .filter(|l| l.is_fresh());
+        for line in lines {
+            let history = self.history.entry(line.currency.clone()).or_default();
+            if history.len() == self.window
+                && let Some(sma) = compute_sma(history)
+            {
+                line.trend = Some(trend_marker(line.value, sma));
+            }
+            if history.len() == self.window {
+                history.pop_front();
+            }
+            history.push_back(line.value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::RateLine;
+
+    fn cycle(tracker: &mut TrendTracker, toman: i64) -> Option<&'static str> {
+        let line = RateLine::new(Currency::Usd, "💵", "دلار", toman, None);
+        let mut sections = [Section::new(vec![line])];
+        tracker.apply(&mut sections);
+        sections[0].lines[0].trend
+    }
+
+    #[test]
+    fn sma_of_partial_and_full_history() {
+        assert_eq!(compute_sma(&VecDeque::new()), None);
+        assert_eq!(compute_sma(&VecDeque::from([100])), Some(100.0));
+        assert_eq!(compute_sma(&VecDeque::from([100, 200])), Some(150.0));
+        assert_eq!(compute_sma(&VecDeque::from([100, 200, 600])), Some(300.0));
+    }
+
+    #[test]
+    fn marker_thresholds() {
+        assert_eq!(trend_marker(100_100, 100_000.0), "➡️");
+        assert_eq!(trend_marker(99_900, 100_000.0), "➡️");
+        assert_eq!(trend_marker(100_101, 100_000.0), "📈");
+        assert_eq!(trend_marker(99_899, 100_000.0), "📉");
+    }
+
+    #[test]
+    fn no_marker_until_window_is_full() {
+        let mut tracker = TrendTracker::new(3);
+        assert_eq!(cycle(&mut tracker, 100_000), None);
+        assert_eq!(cycle(&mut tracker, 100_000), None);
+        assert_eq!(cycle(&mut tracker, 100_000), None);
+        // سه مقدار قبلی حالا کامل‌اند
+        assert_eq!(cycle(&mut tracker, 110_000), Some("📈"));
+    }
+
+    #[test]
+    fn oldest_reading_is_evicted() {
+        let mut tracker = TrendTracker::new(3);
+        for toman in [10_000, 100_000, 100_000] {
+            cycle(&mut tracker, toman);
+        }
+        // میانگین ۷۰٬۰۰۰ → بالاتر
+        assert_eq!(cycle(&mut tracker, 95_000), Some("📈"));
+        // ۱۰٬۰۰۰ بیرون رفته: میانگین ۹۸٬۳۳۳ → پایین‌تر
+        assert_eq!(cycle(&mut tracker, 95_000), Some("📉"));
+        assert_eq!(tracker.history[&Currency::Usd].len(), 3);
+    }
+}
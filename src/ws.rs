@@ -0,0 +1,290 @@
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info};
+
+use crate::api::rates_json;
+use crate::telegram_poller::{RateSnapshot, SharedSnapshot};
+
+/// Snapshots kept for slow clients before they start skipping ahead.
+pub const RATE_UPDATES_BUFFER: usize = 10;
+/// A connection that hasn't answered one ping by the next is dropped.
+pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+// RFC 6455، بخش ۴.۲.۲
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// کلاینت‌ها فقط pong و close می‌فرستن؛ فریم بزرگ‌تر یعنی کلاینت اشتباهی
+const MAX_FRAME_LEN: u64 = 64 * 1024;
+
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+// SHA-1 فقط برای Sec-WebSocket-Accept؛ جای امنیتی استفاده نمی‌شه
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in msg.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (acc, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *acc = acc.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0u8; 20];
+    for (bytes, word) in out.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+fn accept_key(key: &str) -> String {
+    STANDARD.encode(sha1(format!("{}{}", key.trim(), ACCEPT_GUID).as_bytes()))
+}
+
+fn header_has(headers: &HeaderMap, name: header::HeaderName, token: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|v| v.trim().eq_ignore_ascii_case(token))
+}
+
+// سرور فریم‌ها رو بدون mask می‌فرسته
+async fn write_frame<W: AsyncWrite + Unpin>(
+    out: &mut W,
+    opcode: u8,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        n if n < 126 => frame.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    out.write_all(&frame).await?;
+    out.flush().await
+}
+
+// فریم‌های کلاینت همیشه mask دارن؛ تکه‌تکه بودن (fragmentation) برای
+// فریم‌های کنترلی مجاز نیست و بقیه‌ی فریم‌ها هم اینجا دور ریخته می‌شن
+async fn read_frame<R: AsyncRead + Unpin>(input: &mut R) -> std::io::Result<(u8, Vec<u8>)> {
+    let mut head = [0u8; 2];
+    input.read_exact(&mut head).await?;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7F {
+        126 => u64::from(input.read_u16().await?),
+        127 => input.read_u64().await?,
+        n => u64::from(n),
+    };
+    if len > MAX_FRAME_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {} bytes", len),
+        ));
+    }
+    let mut mask = [0u8; 4];
+    if masked {
+        input.read_exact(&mut mask).await?;
+    }
+    let mut payload = vec![0u8; len as usize];
+    input.read_exact(&mut payload).await?;
+    for (i, b) in payload.iter_mut().enumerate() {
+        *b ^= mask[i % 4];
+    }
+    Ok((opcode, payload))
+}
+
+enum Incoming {
+    Ping(Vec<u8>),
+    Pong,
+    Close,
+}
+
+async fn read_loop<R: AsyncRead + Unpin>(mut input: R, tx: mpsc::Sender<Incoming>) {
+    loop {
+        let event = match read_frame(&mut input).await {
+            Ok((OP_PING, payload)) => Incoming::Ping(payload),
+            Ok((OP_PONG, _)) => Incoming::Pong,
+            Ok((OP_CLOSE, _)) | Err(_) => Incoming::Close,
+            Ok(_) => continue,
+        };
+        let closing = matches!(event, Incoming::Close);
+        if tx.send(event).await.is_err() || closing {
+            return;
+        }
+    }
+}
+
+/// Upgrades `GET /ws/rates` to a WebSocket that streams the rates.
+///
+/// Every message is a text frame holding the same JSON as
+/// `GET /api/v1/rates`:
+///
+/// ```json
+/// {"timestamp": "2025-01-01T12:00:00Z",
+///  "rates": {"USD": {"toman": 58000, "changed_pct": 0.5, "stale": false},
+///            "OUNCE": {"usd": 2650.4, "changed_pct": null, "stale": false}}}
+/// ```
+///
+/// The current snapshot is sent right after connecting, then one message per
+/// cycle. A client that falls more than [`RATE_UPDATES_BUFFER`] cycles
+/// behind skips straight to the latest snapshot. The server pings every
+/// [`PING_INTERVAL`] and drops a client that didn't pong in between. Any
+/// text or binary frame from the client is ignored.
+pub fn upgrade(
+    mut req: Request,
+    snapshot: SharedSnapshot,
+    updates: broadcast::Receiver<RateSnapshot>,
+    shutdown: watch::Receiver<bool>,
+) -> Response {
+    let headers = req.headers();
+    let key = headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let valid = header_has(headers, header::UPGRADE, "websocket")
+        && header_has(headers, header::CONNECTION, "upgrade")
+        && header_has(headers, header::SEC_WEBSOCKET_VERSION, "13");
+    let (Some(key), true) = (key, valid) else {
+        return (StatusCode::BAD_REQUEST, "expected a WebSocket upgrade").into_response();
+    };
+
+    let on_upgrade = hyper::upgrade::on(&mut req);
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => serve(TokioIo::new(upgraded), snapshot, updates, shutdown).await,
+            Err(e) => debug!(error = %e, "WebSocket upgrade failed"),
+        }
+    });
+
+    let mut response = StatusCode::SWITCHING_PROTOCOLS.into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+    if let Ok(accept) = HeaderValue::from_str(&accept_key(&key)) {
+        headers.insert(header::SEC_WEBSOCKET_ACCEPT, accept);
+    }
+    response
+}
+
+async fn serve<S: AsyncRead + AsyncWrite + Send + 'static>(
+    stream: S,
+    snapshot: SharedSnapshot,
+    mut updates: broadcast::Receiver<RateSnapshot>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let (input, mut out) = tokio::io::split(stream);
+    let (tx, mut incoming) = mpsc::channel(8);
+    let reader = tokio::spawn(read_loop(input, tx));
+    info!("🔌 WebSocket client connected");
+
+    let first = rates_json(&*snapshot.read().await);
+    let mut ok = write_frame(&mut out, OP_TEXT, first.as_bytes())
+        .await
+        .is_ok();
+    let mut ping = interval(PING_INTERVAL);
+    ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // اولین تیک همون لحظه‌ست
+    ping.tick().await;
+    let mut answered = true;
+
+    while ok {
+        ok = tokio::select! {
+            update = updates.recv() => match update {
+                Ok(update) => {
+                    write_frame(&mut out, OP_TEXT, rates_json(&update).as_bytes()).await.is_ok()
+                }
+                // عقب‌افتاده‌ها فقط آخرین وضعیت رو می‌گیرن
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "WebSocket client lagged");
+                    let latest = rates_json(&*snapshot.read().await);
+                    write_frame(&mut out, OP_TEXT, latest.as_bytes()).await.is_ok()
+                }
+                Err(RecvError::Closed) => false,
+            },
+            _ = ping.tick() => {
+                if !answered {
+                    debug!("WebSocket client missed a pong");
+                    false
+                } else {
+                    answered = false;
+                    write_frame(&mut out, OP_PING, &[]).await.is_ok()
+                }
+            },
+            event = incoming.recv() => match event {
+                Some(Incoming::Ping(payload)) => {
+                    write_frame(&mut out, OP_PONG, &payload).await.is_ok()
+                }
+                Some(Incoming::Pong) => {
+                    answered = true;
+                    true
+                }
+                Some(Incoming::Close) | None => false,
+            },
+            // Ref خود watch نباید از روی await رد بشه
+            _ = async { shutdown.wait_for(|stop| *stop).await.is_ok() } => false,
+        };
+    }
+
+    let _ = write_frame(&mut out, OP_CLOSE, &1000u16.to_be_bytes()).await;
+    reader.abort();
+    info!("🔌 WebSocket client disconnected");
+}